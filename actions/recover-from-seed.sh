@@ -0,0 +1,34 @@
+#!/bin/sh
+
+set -e
+
+cat > input.json
+SEED=$(jq -r '.["cipher-seed-mnemonic"]' input.json)
+PASSPHRASE=$(jq -r '.["aezeed-passphrase"] // empty' input.json)
+CHANNEL_BACKUP=$(jq -r '.["channel-backup"]' input.json | tr -d ' \n')
+rm input.json
+
+if [ -e "/root/.lnd/pwd.dat" ]; then
+  echo "Error: Existing LND wallet found on StartOS. Disaster recovery can only be started on a fresh install of LND BEFORE ever starting the LND service on StartOS." >&2
+  exit 1
+fi
+if [ $(echo "$SEED" | wc -w) -ne 24 ]; then
+  echo "Error: The Aezeed Cipher Seed must consist of exactly 24 words." >&2
+  exit 1
+fi
+if ! echo "$CHANNEL_BACKUP" | base64 -d > /dev/null 2>&1; then
+  echo "Error: The channel backup is not valid base64." >&2
+  exit 1
+fi
+
+mkdir -p /root/.lnd/start9
+jq -n \
+  --arg seed "$SEED" \
+  --arg passphrase "$PASSPHRASE" \
+  --arg backup "$CHANNEL_BACKUP" \
+  '{
+    "cipher-seed-mnemonic": ($seed | split(" ") | map(select(. != ""))),
+    "aezeed-passphrase": (if $passphrase == "" then null else $passphrase end),
+    "multi-chan-backup": $backup
+  }' > /root/.lnd/start9/recovery.yaml
+echo '{"version":"0","message":"Disaster recovery has been scheduled. Start LND to recover the on-chain wallet from the seed, after which all channels in the backup will be force closed by their peers. Progress is recorded in start9/recovery-status.yaml.","value":null,"copyable":false,"qr":false}'
\ No newline at end of file
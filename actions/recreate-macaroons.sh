@@ -2,12 +2,16 @@
 
 set -e
 
-rm -f /root/.lnd/data/chain/bitcoin/mainnet/*.macaroon >/dev/null
-rm -f /root/.lnd/public/*.macaroon >/dev/null
+# Deleting the files alone would leave every issued macaroon valid, so rotate the root keys instead.
+# Profile keys are replaced right away; root key 0 can only be replaced while unlocking the wallet.
+if ! output=$(configurator macaroons rotate 2>&1); then
+  >&2 echo "$output"
+  exit 1
+fi
 
 action_result_running="    {
     \"version\": \"0\",
-    \"message\": \"Existing macaroons have been deleted. Restarting LND to recreate macaroons.\",
+    \"message\": \"All macaroon root keys are being rotated. Restarting LND to recreate its own macaroons; previously issued macaroons no longer work.\",
     \"value\": null,
     \"copyable\": false,
     \"qr\": false
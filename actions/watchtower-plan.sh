@@ -0,0 +1,24 @@
+#!/bin/sh
+
+set -e
+
+# Only reports; the configurator applies the same plan when LND starts.
+# Warnings go to stderr, so stdout is nothing but the JSON plan.
+changes=$(configurator watchtowers --dry-run)
+
+echo "$changes" | jq '{
+  "version": "0",
+  "message": (if length == 0
+    then "The watchtower client already matches the configured towers."
+    else "These changes will be made on the next restart to match the configured towers."
+    end),
+  "value": (if length == 0 then null else map(
+    if .action == "add" then "add \(.uri)"
+    elif .action == "remove" then "remove \(.pubkey)"
+    else "remove address \(.address) from \(.pubkey)"
+    end) | join("\n") end),
+  "copyable": false,
+  "qr": false
+}'
+
+exit 0
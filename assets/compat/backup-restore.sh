@@ -1,5 +1,12 @@
 #!/bin/sh
 
+# the latest node epoch is excluded from backups and must outlive the restore
+if [ -e /root/.lnd/start9/node-epoch.yaml ]; then
+  cp /root/.lnd/start9/node-epoch.yaml /tmp/node-epoch.yaml
+fi
 compat duplicity restore /mnt/backup /root/.lnd
 mkdir -p /root/.lnd/start9
+if [ -e /tmp/node-epoch.yaml ]; then
+  mv /tmp/node-epoch.yaml /root/.lnd/start9/node-epoch.yaml
+fi
 touch /root/.lnd/start9/restore.yaml
\ No newline at end of file
@@ -0,0 +1,161 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lncli;
+
+/// Stamp stored next to channel.backup, so a restore brings back the epoch the
+/// backup was taken at. channel.db itself is never backed up or restored.
+const STAMP_PATH: &str = "data/chain/bitcoin/mainnet/node-epoch.yaml";
+/// Latest epoch ever seen for this node. Excluded from backups and preserved across restores
+const LATEST_PATH: &str = "start9/node-epoch.yaml";
+
+/// The open channels of a node at one point in time. Only channel points matter:
+/// channel.backup recovers a channel by its point, whatever its update count.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NodeEpoch {
+    identity_pubkey: String,
+    recorded_at: u64,
+    channels: BTreeSet<String>,
+}
+impl NodeEpoch {
+    pub fn fetch() -> Result<Self, anyhow::Error> {
+        let info = lncli::get_info()?;
+        let channels = lncli::list_channels()?
+            .into_iter()
+            .map(|c| c.channel_point)
+            .collect();
+        Ok(NodeEpoch {
+            identity_pubkey: info.identity_pubkey,
+            recorded_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            channels,
+        })
+    }
+
+    fn load(path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_yaml::from_reader(std::fs::File::open(path)?)?))
+    }
+
+    fn store(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let tmp = path.with_extension("yaml.tmp");
+        std::fs::write(&tmp, serde_yaml::to_string(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Channels in `latest` that `self` does not know about, i.e. opened after it was recorded
+    fn stale_channels(&self, latest: &NodeEpoch) -> Vec<String> {
+        latest
+            .channels
+            .iter()
+            .filter(|point| !self.channels.contains(*point))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Records the current epoch both beside channel.backup and in the latest-known location
+pub fn record(base_path: &Path) -> Result<(), anyhow::Error> {
+    let epoch = NodeEpoch::fetch()?;
+    let stamp = base_path.join(STAMP_PATH);
+    if stamp.parent().is_some_and(|dir| dir.exists()) {
+        epoch.store(&stamp)?;
+    }
+    epoch.store(&base_path.join(LATEST_PATH))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RestoreCheck {
+    /// no stamp came with the backup, or there is nothing to compare it against
+    Unknown,
+    /// the backup knows every channel of the latest recorded state
+    Current,
+    /// these channels were opened after the backup was taken, so the restored
+    /// channel.backup cannot recover them
+    Stale(Vec<String>),
+}
+
+/// Compares the epoch restored with the backup against the latest one seen on this server
+pub fn check_restore(base_path: &Path) -> Result<RestoreCheck, anyhow::Error> {
+    let latest = match NodeEpoch::load(&base_path.join(LATEST_PATH))? {
+        Some(latest) => latest,
+        None => return Ok(RestoreCheck::Unknown),
+    };
+    let restored = match NodeEpoch::load(&base_path.join(STAMP_PATH))? {
+        Some(restored) if restored.identity_pubkey == latest.identity_pubkey => restored,
+        _ => return Ok(RestoreCheck::Unknown),
+    };
+    let stale = restored.stale_channels(&latest);
+    if stale.is_empty() {
+        Ok(RestoreCheck::Current)
+    } else {
+        Ok(RestoreCheck::Stale(stale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epoch(identity_pubkey: &str, channels: &[&str]) -> NodeEpoch {
+        NodeEpoch {
+            identity_pubkey: identity_pubkey.to_owned(),
+            recorded_at: 0,
+            channels: channels.iter().map(|point| (*point).to_owned()).collect(),
+        }
+    }
+
+    fn base_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("epoch-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(path.join("start9")).unwrap();
+        std::fs::create_dir_all(path.join("data/chain/bitcoin/mainnet")).unwrap();
+        path
+    }
+
+    #[test]
+    fn stale_channels_are_the_ones_opened_later() {
+        let restored = epoch("node", &["a:0", "b:1"]);
+        let latest = epoch("node", &["a:0", "c:0"]);
+        assert_eq!(restored.stale_channels(&latest), vec!["c:0".to_owned()]);
+        assert!(latest.stale_channels(&latest).is_empty());
+        assert!(restored.stale_channels(&epoch("node", &[])).is_empty());
+    }
+
+    #[test]
+    fn check_restore_compares_stamp_with_latest() {
+        let base = base_path("check");
+        assert_eq!(check_restore(&base).unwrap(), RestoreCheck::Unknown);
+
+        epoch("node", &["a:0", "c:0"])
+            .store(&base.join(LATEST_PATH))
+            .unwrap();
+        assert_eq!(check_restore(&base).unwrap(), RestoreCheck::Unknown);
+
+        epoch("node", &["a:0"])
+            .store(&base.join(STAMP_PATH))
+            .unwrap();
+        assert_eq!(
+            check_restore(&base).unwrap(),
+            RestoreCheck::Stale(vec!["c:0".to_owned()])
+        );
+
+        epoch("node", &["a:0", "c:0"])
+            .store(&base.join(STAMP_PATH))
+            .unwrap();
+        assert_eq!(check_restore(&base).unwrap(), RestoreCheck::Current);
+
+        epoch("other", &[]).store(&base.join(STAMP_PATH)).unwrap();
+        assert_eq!(check_restore(&base).unwrap(), RestoreCheck::Unknown);
+
+        std::fs::write(base.join(STAMP_PATH), "not: [an epoch").unwrap();
+        assert!(check_restore(&base).is_err());
+        std::fs::remove_dir_all(base).unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use std::process::Command;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+
+/// Runs `lncli --rpcserver=lnd.embassy <args>` and returns what it printed.
+pub fn run(args: &[&str]) -> Result<Vec<u8>, anyhow::Error> {
+    let output = Command::new("lncli")
+        .arg("--rpcserver=lnd.embassy")
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "lncli {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Runs `lncli --rpcserver=lnd.embassy <args>` and parses its JSON output.
+pub fn call<T: DeserializeOwned>(args: &[&str]) -> Result<T, anyhow::Error> {
+    Ok(serde_json::from_slice(&run(args)?)?)
+}
+
+/// lncli prints 64-bit integers as JSON strings
+pub fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetInfo {
+    pub identity_pubkey: String,
+    pub alias: String,
+    pub block_height: u32,
+    pub synced_to_chain: bool,
+    pub synced_to_graph: bool,
+}
+
+pub fn get_info() -> Result<GetInfo, anyhow::Error> {
+    call(&["getinfo"])
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Channel {
+    pub channel_point: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListChannels {
+    channels: Vec<Channel>,
+}
+
+pub fn list_channels() -> Result<Vec<Channel>, anyhow::Error> {
+    Ok(call::<ListChannels>(&["listchannels"])?.channels)
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::str::FromStr;
+
+const SCHEME: &str = "lndconnect://";
+
+/// An `lndconnect://host:port?cert=...&macaroon=...` URI, as understood by Zeus, Zap and friends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LndConnect {
+    pub host: String,
+    pub port: u16,
+    /// DER encoded. Left out for setups where the certificate is signed by a CA the client trusts
+    pub cert: Option<Vec<u8>>,
+    pub macaroon: Vec<u8>,
+}
+impl LndConnect {
+    pub fn new(host: impl Into<String>, port: u16, macaroon: Vec<u8>) -> Self {
+        LndConnect {
+            host: host.into(),
+            port,
+            cert: None,
+            macaroon,
+        }
+    }
+
+    pub fn with_cert(mut self, cert: Vec<u8>) -> Self {
+        self.cert = Some(cert);
+        self
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+impl fmt::Display for LndConnect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", SCHEME)?;
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)?;
+        } else {
+            write!(f, "{}:{}", self.host, self.port)?;
+        }
+        write!(f, "?")?;
+        if let Some(cert) = &self.cert {
+            write!(f, "cert={}&", encode(cert))?;
+        }
+        write!(f, "macaroon={}", encode(&self.macaroon))
+    }
+}
+
+impl FromStr for LndConnect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| anyhow::anyhow!("lndconnect URI must start with {}", SCHEME))?;
+        let (authority, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (host, port) = authority
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow::anyhow!("lndconnect URI is missing a port"))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            anyhow::bail!("lndconnect URI is missing a host");
+        }
+        let mut cert = None;
+        let mut macaroon = None;
+        for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            let value = base64::decode_config(value, base64::URL_SAFE_NO_PAD)?;
+            match key {
+                "cert" => cert = Some(value),
+                "macaroon" => macaroon = Some(value),
+                _ => (),
+            }
+        }
+        Ok(LndConnect {
+            host: host.to_owned(),
+            port: port.parse()?,
+            cert,
+            macaroon: macaroon
+                .ok_or_else(|| anyhow::anyhow!("lndconnect URI is missing a macaroon"))?,
+        })
+    }
+}
+
+/// The DER contents of a PEM certificate, which is what lndconnect carries
+pub fn cert_der(pem: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(pem)
+        .map_err(|e| anyhow::anyhow!("Invalid PEM certificate: {}", e))?;
+    Ok(pem.contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_with_cert() {
+        let uri = LndConnect::new(
+            "abcdefghijklmnop.onion",
+            10009,
+            vec![0x02, 0x01, 0xfe, 0xff],
+        )
+        .with_cert(vec![0x30, 0x82, 0x01, 0x0a, 0xfb]);
+        let s = uri.to_string();
+        assert_eq!(
+            s,
+            "lndconnect://abcdefghijklmnop.onion:10009?cert=MIIBCvs&macaroon=AgH-_w"
+        );
+        assert_eq!(s.parse::<LndConnect>().unwrap(), uri);
+    }
+
+    #[test]
+    fn round_trip_without_cert() {
+        let uri = LndConnect::new("172.18.0.5", 8080, vec![1, 2, 3]);
+        let s = uri.to_string();
+        assert!(!s.contains("cert="));
+        assert_eq!(s.parse::<LndConnect>().unwrap(), uri);
+    }
+
+    #[test]
+    fn round_trip_ipv6() {
+        let uri = LndConnect::new("fd00::5", 10009, vec![1, 2, 3]);
+        let s = uri.to_string();
+        assert!(s.starts_with("lndconnect://[fd00::5]:10009?"));
+        assert_eq!(s.parse::<LndConnect>().unwrap(), uri);
+    }
+
+    #[test]
+    fn rejects_missing_macaroon() {
+        assert!("lndconnect://host:10009?cert=AAAA"
+            .parse::<LndConnect>()
+            .is_err());
+        assert!("https://host:10009?macaroon=AAAA"
+            .parse::<LndConnect>()
+            .is_err());
+    }
+}
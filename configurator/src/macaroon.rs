@@ -0,0 +1,296 @@
+//! Offline decoding of the macaroon v2 binary format, as LND bakes it.
+//! health-check/src/macaroon.rs carries a smaller copy of this parser; keep the two in sync.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Caveat {
+    /// the predicate, e.g. `time-before 2030-01-01T00:00:00Z` or `ipaddr 172.18.0.5`
+    pub id: String,
+    /// only set for third-party caveats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Macaroon {
+    pub location: Option<String>,
+    pub root_key_id: u64,
+    /// `entity:action` pairs
+    pub permissions: Vec<String>,
+    pub caveats: Vec<Caveat>,
+}
+
+const FIELD_EOS: u8 = 0;
+const FIELD_LOCATION: u8 = 1;
+const FIELD_IDENTIFIER: u8 = 2;
+const FIELD_SIGNATURE: u8 = 6;
+
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, anyhow::Error> {
+        let (b, rest) = self
+            .0
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("truncated macaroon"))?;
+        self.0 = rest;
+        Ok(*b)
+    }
+
+    fn varint(&mut self) -> Result<u64, anyhow::Error> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            value |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow::anyhow!("varint too long"))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], anyhow::Error> {
+        let len = self.varint()? as usize;
+        if self.0.len() < len {
+            return Err(anyhow::anyhow!("truncated field"));
+        }
+        let (data, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(data)
+    }
+
+    /// The fields of one section, up to its end-of-section marker
+    fn section(&mut self) -> Result<Vec<(u8, &'a [u8])>, anyhow::Error> {
+        let mut fields = Vec::new();
+        loop {
+            match self.byte()? {
+                FIELD_EOS => return Ok(fields),
+                field => fields.push((field, self.bytes()?)),
+            }
+        }
+    }
+}
+
+/// The length-delimited fields of a protobuf message, by field number
+fn proto_fields(data: &[u8]) -> Result<Vec<(u64, &[u8])>, anyhow::Error> {
+    let mut reader = Reader(data);
+    let mut fields = Vec::new();
+    while !reader.0.is_empty() {
+        let key = reader.varint()?;
+        match key & 7 {
+            0 => {
+                reader.varint()?;
+            }
+            2 => fields.push((key >> 3, reader.bytes()?)),
+            wire_type => return Err(anyhow::anyhow!("unexpected wire type {}", wire_type)),
+        }
+    }
+    Ok(fields)
+}
+
+/// LND identifiers are a version byte followed by a MacaroonId protobuf message:
+/// nonce (1), storage id (2) holding the decimal root key id, and ops (3)
+fn parse_identifier(identifier: &[u8]) -> Result<(u64, Vec<String>), anyhow::Error> {
+    let id = match identifier.split_first() {
+        Some((3, id)) => id,
+        _ => return Err(anyhow::anyhow!("unsupported macaroon identifier")),
+    };
+    let mut root_key_id = 0;
+    let mut permissions = Vec::new();
+    for (field, value) in proto_fields(id)? {
+        match field {
+            2 => root_key_id = std::str::from_utf8(value)?.parse()?,
+            3 => {
+                let mut entity = "";
+                let mut actions = Vec::new();
+                for (field, value) in proto_fields(value)? {
+                    match field {
+                        1 => entity = std::str::from_utf8(value)?,
+                        2 => actions.push(std::str::from_utf8(value)?),
+                        _ => (),
+                    }
+                }
+                permissions.extend(actions.iter().map(|a| format!("{}:{}", entity, a)));
+            }
+            _ => (),
+        }
+    }
+    Ok((root_key_id, permissions))
+}
+
+impl Macaroon {
+    pub fn decode(mac: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut reader = Reader(mac);
+        let version = reader.byte()?;
+        if version != 2 {
+            return Err(anyhow::anyhow!("unsupported macaroon version {}", version));
+        }
+        let mut location = None;
+        let mut identifier = None;
+        for (field, value) in reader.section()? {
+            match field {
+                FIELD_LOCATION => location = Some(String::from_utf8(value.to_vec())?),
+                FIELD_IDENTIFIER => identifier = Some(value),
+                _ => (),
+            }
+        }
+        let (root_key_id, permissions) = parse_identifier(
+            identifier.ok_or_else(|| anyhow::anyhow!("macaroon has no identifier"))?,
+        )?;
+        let mut caveats = Vec::new();
+        loop {
+            let section = reader.section()?;
+            if section.is_empty() {
+                break;
+            }
+            let mut caveat = Caveat {
+                id: String::new(),
+                location: None,
+            };
+            for (field, value) in section {
+                match field {
+                    FIELD_LOCATION => caveat.location = Some(String::from_utf8(value.to_vec())?),
+                    FIELD_IDENTIFIER => caveat.id = String::from_utf8_lossy(value).into_owned(),
+                    _ => (),
+                }
+            }
+            caveats.push(caveat);
+        }
+        match reader.byte()? {
+            FIELD_SIGNATURE => {
+                reader.bytes()?;
+            }
+            field => return Err(anyhow::anyhow!("expected signature, found field {}", field)),
+        }
+        Ok(Macaroon {
+            location,
+            root_key_id,
+            permissions,
+            caveats,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // laid out byte for byte like LND bakes them: location "lnd", a version 3
+    // identifier with a 16 byte nonce, and a 32 byte signature
+    const ADMIN: &str = concat!(
+        "0201036c6e6402f801030a10ca978112ca1bbdcafac231b39a23dc4d1201301a",
+        "160a0761646472657373120472656164120577726974651a130a04696e666f12",
+        "0472656164120577726974651a170a08696e766f696365731204726561641205",
+        "77726974651a210a086d616361726f6f6e120867656e65726174651204726561",
+        "64120577726974651a160a076d65737361676512047265616412057772697465",
+        "1a170a086f6666636861696e120472656164120577726974651a160a076f6e63",
+        "6861696e120472656164120577726974651a140a057065657273120472656164",
+        "120577726974651a180a067369676e6572120867656e65726174651204726561",
+        "64000006204cf6829aa93728e8f3c97df913fb1bfa95fe5810e2933a05943f83",
+        "12a98d9cf2",
+    );
+    const READONLY: &str = concat!(
+        "0201036c6e6402ac01030a10454349e422f05297191ead13e21d3db51201301a",
+        "0f0a07616464726573731204726561641a0c0a04696e666f1204726561641a10",
+        "0a08696e766f696365731204726561641a100a086d616361726f6f6e12047265",
+        "61641a0f0a076d6573736167651204726561641a100a086f6666636861696e12",
+        "04726561641a0f0a076f6e636861696e1204726561641a0d0a05706565727312",
+        "04726561641a0e0a067369676e657212047265616400000620cc8844298c08e2",
+        "fb7ba75080b9fad6fbd23d63bf3534c713e87ad87cee8f5b57",
+    );
+    /// info:read and offchain:read under root key 1234567, constrained with
+    /// `lncli constrainmacaroon --timeout ... --ip_address 172.18.0.5`
+    const CONSTRAINED: &str = concat!(
+        "0201036c6e64023c030a102e7d2c03a9507ae265ecf5b5356885a51207313233",
+        "343536371a0c0a04696e666f1204726561641a100a086f6666636861696e1204",
+        "7265616400022074696d652d6265666f726520323033302d30312d3031543030",
+        "3a30303a30305a000211697061646472203137322e31382e302e3500000620e0",
+        "b9a8799f32453a478c9122f8b83cee68e16db18f493ac81bc1d474594b5df4",
+    );
+
+    fn bytes(fixture: &str) -> Vec<u8> {
+        hex::decode(fixture).unwrap()
+    }
+
+    const ADMIN_PERMISSIONS: &[&str] = &[
+        "address:read",
+        "address:write",
+        "info:read",
+        "info:write",
+        "invoices:read",
+        "invoices:write",
+        "macaroon:generate",
+        "macaroon:read",
+        "macaroon:write",
+        "message:read",
+        "message:write",
+        "offchain:read",
+        "offchain:write",
+        "onchain:read",
+        "onchain:write",
+        "peers:read",
+        "peers:write",
+        "signer:generate",
+        "signer:read",
+    ];
+
+    #[test]
+    fn decodes_admin() {
+        let mac = Macaroon::decode(&bytes(ADMIN)).unwrap();
+        assert_eq!(mac.location.as_deref(), Some("lnd"));
+        assert_eq!(mac.root_key_id, 0);
+        assert_eq!(mac.permissions, ADMIN_PERMISSIONS);
+        assert!(mac.caveats.is_empty());
+    }
+
+    #[test]
+    fn decodes_readonly() {
+        let mac = Macaroon::decode(&bytes(READONLY)).unwrap();
+        assert_eq!(mac.root_key_id, 0);
+        assert_eq!(mac.permissions.len(), 9);
+        assert!(mac.permissions.iter().all(|p| p.ends_with(":read")));
+        assert!(mac.caveats.is_empty());
+    }
+
+    #[test]
+    fn decodes_constrained() {
+        let mac = Macaroon::decode(&bytes(CONSTRAINED)).unwrap();
+        assert_eq!(mac.root_key_id, 1234567);
+        assert_eq!(mac.permissions, vec!["info:read", "offchain:read"]);
+        assert_eq!(
+            mac.caveats,
+            vec![
+                Caveat {
+                    id: "time-before 2030-01-01T00:00:00Z".to_owned(),
+                    location: None,
+                },
+                Caveat {
+                    id: "ipaddr 172.18.0.5".to_owned(),
+                    location: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        for fixture in &[ADMIN, READONLY, CONSTRAINED] {
+            let mac = bytes(fixture);
+            for len in 0..mac.len() {
+                assert!(Macaroon::decode(&mac[..len]).is_err(), "{} bytes", len);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bad_root_key_id() {
+        // storage id "0" replaced with "x"
+        let mac = bytes(&ADMIN.replacen("1201301a", "1201781a", 1));
+        assert!(Macaroon::decode(&mac).is_err());
+        let mut mac = bytes(ADMIN);
+        mac[0] = 1;
+        assert!(Macaroon::decode(&mac).is_err());
+    }
+}
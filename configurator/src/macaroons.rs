@@ -0,0 +1,605 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::macaroon::Macaroon;
+use crate::{lncli, rest};
+
+const MACAROON_DIR: &str = "data/chain/bitcoin/mainnet";
+const PUBLIC_DIR: &str = "public";
+/// Which root key and permissions every published profile was baked with
+const STATE_PATH: &str = "start9/macaroons.yaml";
+/// Baked from root key 0 for the health checks
+const HEALTH_CHECK_MACAROON_PATH: &str = "start9/health-check.macaroon";
+/// Everything the health checks read, and nothing that can move funds or change the node
+const HEALTH_CHECK_PERMISSIONS: &[&str] =
+    &["info:read", "offchain:read", "onchain:read", "peers:read"];
+/// Present while root key 0 waits to be rotated by the next wallet unlock
+const ROTATION_REQUEST_PATH: &str = "start9/rotate-root-key";
+/// The macaroons LND creates itself, all baked from root key 0
+const DEFAULT_MACAROONS: &[&str] = &[
+    "admin",
+    "readonly",
+    "invoice",
+    "chainnotifier",
+    "signer",
+    "walletkit",
+    "router",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    Readonly,
+    /// create invoices and receive addresses, nothing else
+    Invoices,
+    /// pay and receive over lightning. LND has no caveat to cap the amount
+    Payments,
+    Custom,
+}
+impl Preset {
+    fn permissions(self) -> &'static [&'static str] {
+        match self {
+            Preset::Readonly => &[
+                "info:read",
+                "onchain:read",
+                "offchain:read",
+                "address:read",
+                "message:read",
+                "peers:read",
+                "invoices:read",
+                "signer:read",
+                "macaroon:read",
+            ],
+            Preset::Invoices => &[
+                "invoices:read",
+                "invoices:write",
+                "address:read",
+                "address:write",
+                "onchain:read",
+            ],
+            Preset::Payments => &[
+                "info:read",
+                "offchain:read",
+                "offchain:write",
+                "invoices:read",
+                "invoices:write",
+                "peers:read",
+            ],
+            Preset::Custom => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MacaroonProfile {
+    pub name: String,
+    pub preset: Preset,
+    #[serde(default)]
+    pub custom_permissions: Vec<String>,
+    /// hours until the published macaroon expires. It is re-issued well before that
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// package id of the dependent; the macaroon only works from its container IP
+    #[serde(default)]
+    pub ip_lock: Option<String>,
+}
+impl MacaroonProfile {
+    fn permissions(&self) -> Vec<String> {
+        let mut perms: Vec<String> = match self.preset {
+            Preset::Custom => self.custom_permissions.clone(),
+            preset => preset.permissions().iter().map(|p| p.to_string()).collect(),
+        };
+        perms.sort();
+        perms.dedup();
+        perms
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MacaroonsConfig {
+    pub publish_default_macaroons: bool,
+    pub profiles: Vec<MacaroonProfile>,
+}
+impl Default for MacaroonsConfig {
+    fn default() -> Self {
+        MacaroonsConfig {
+            publish_default_macaroons: true,
+            profiles: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BakedProfile {
+    pub root_key_id: u64,
+    pub permissions: Vec<String>,
+    /// unix seconds
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub ip_address: Option<IpAddr>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct MacaroonState {
+    pub profiles: BTreeMap<String, BakedProfile>,
+}
+impl MacaroonState {
+    pub fn load(base_path: &Path) -> Result<Self, anyhow::Error> {
+        let path = base_path.join(STATE_PATH);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_yaml::from_reader(std::fs::File::open(path)?)?)
+    }
+
+    pub fn store(&self, base_path: &Path) -> Result<(), anyhow::Error> {
+        let path = base_path.join(STATE_PATH);
+        let tmp = path.with_extension("yaml.tmp");
+        std::fs::write(&tmp, serde_yaml::to_string(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Root key 0 belongs to LND's own macaroons, every profile gets its own after that.
+    /// Other apps can bake from ids of their own through admin.macaroon, so every id LND
+    /// holds a key for is avoided; `taken` caches that list for one pass of `publish`.
+    fn fresh_root_key_id(&self, taken: &mut Option<BTreeSet<u64>>) -> Result<u64, anyhow::Error> {
+        let taken = match taken {
+            Some(taken) => taken,
+            None => taken.insert(list_root_keys()?.into_iter().collect()),
+        };
+        let id = (1..=u64::MAX)
+            .find(|id| !taken.contains(id) && self.profiles.values().all(|p| p.root_key_id != *id))
+            .ok_or_else(|| anyhow::anyhow!("no root key id left"))?;
+        taken.insert(id);
+        Ok(id)
+    }
+}
+
+#[derive(Deserialize)]
+struct BakeMacaroonRes {
+    macaroon: String,
+}
+
+/// Bakes a macaroon with `permissions` (`entity:action`) from its own root key
+pub fn bake(root_key_id: u64, permissions: &[String]) -> Result<Vec<u8>, anyhow::Error> {
+    let permissions = permissions
+        .iter()
+        .map(|p| match p.split_once(':') {
+            Some((entity, action)) => Ok(serde_json::json!({
+                "entity": entity,
+                "action": action,
+            })),
+            None => Err(anyhow::anyhow!(
+                "Invalid permission {:?}, expected entity:action",
+                p
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let res: BakeMacaroonRes = rest::post_as(
+        &rest::admin_macaroon()?,
+        "/v1/macaroon",
+        &serde_json::json!({
+            "permissions": permissions,
+            "root_key_id": root_key_id.to_string(),
+        }),
+    )?;
+    Ok(hex::decode(res.macaroon)?)
+}
+
+/// Deletes a root key, which invalidates every macaroon baked from it
+pub fn delete_root_key(root_key_id: u64) -> Result<(), anyhow::Error> {
+    rest::delete::<serde_json::Value>(&format!("/v1/macaroon/{}", root_key_id))?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct RootKeyIds {
+    #[serde(default)]
+    root_key_ids: Vec<String>,
+}
+
+/// Every root key LND holds, which bounds every macaroon that can still be valid
+pub fn list_root_keys() -> Result<Vec<u64>, anyhow::Error> {
+    rest::get::<RootKeyIds>("/v1/macaroon/ids")?
+        .root_key_ids
+        .iter()
+        .map(|id| Ok(id.parse()?))
+        .collect()
+}
+
+/// Bakes the macaroon the health checks use, unless it already exists
+pub fn ensure_health_check_macaroon(base_path: &Path) -> Result<(), anyhow::Error> {
+    let path = base_path.join(HEALTH_CHECK_MACAROON_PATH);
+    if path.exists() {
+        return Ok(());
+    }
+    println!("baking health check macaroon...");
+    let permissions: Vec<String> = HEALTH_CHECK_PERMISSIONS
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    write_atomic(&path, &bake(0, &permissions)?)
+}
+
+/// LND refuses to delete root key 0 over RPC; it can only be replaced while unlocking
+/// the wallet, with changepassword's `new_macaroon_root_key`. So this only asks the
+/// next start to do that.
+pub fn request_default_rotation(base_path: &Path) -> Result<(), anyhow::Error> {
+    std::fs::write(base_path.join(ROTATION_REQUEST_PATH), "")?;
+    Ok(())
+}
+
+pub fn default_rotation_requested(base_path: &Path) -> bool {
+    base_path.join(ROTATION_REQUEST_PATH).exists()
+}
+
+/// Deletes every macaroon file baked from root key 0, so LND bakes them again from the new
+/// key once the wallet is unlocked. Call right before unlocking with a new root key.
+pub fn clear_default_macaroons(base_path: &Path) -> Result<(), anyhow::Error> {
+    for name in DEFAULT_MACAROONS {
+        let file = format!("{}.macaroon", name);
+        for dir in &[MACAROON_DIR, PUBLIC_DIR] {
+            match std::fs::remove_file(base_path.join(dir).join(&file)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+        }
+    }
+    let _ = std::fs::remove_file(base_path.join(HEALTH_CHECK_MACAROON_PATH));
+    Ok(())
+}
+
+/// Called once the wallet was unlocked with a new root key
+pub fn finish_default_rotation(base_path: &Path) -> Result<(), anyhow::Error> {
+    std::fs::remove_file(base_path.join(ROTATION_REQUEST_PATH))?;
+    Ok(())
+}
+
+/// Deletes the given root keys, then re-bakes and republishes every macaroon that was baked from one of them.
+/// Root key 0 is only scheduled for rotation, see `request_default_rotation`.
+pub fn revoke(
+    base_path: &Path,
+    config: &MacaroonsConfig,
+    root_key_ids: &[u64],
+) -> Result<(), anyhow::Error> {
+    let mut state = MacaroonState::load(base_path)?;
+    for id in root_key_ids {
+        if *id == 0 {
+            request_default_rotation(base_path)?;
+            println!("Root key 0 will be rotated when LND restarts");
+            continue;
+        }
+        delete_root_key(*id)?;
+        let owners: Vec<String> = state
+            .profiles
+            .iter()
+            .filter(|(_, p)| p.root_key_id == *id)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in owners {
+            // forgetting the profile makes `publish` bake it again from a fresh root key
+            state.profiles.remove(&name);
+            println!("Revoked macaroon profile {}", name);
+        }
+        state.store(base_path)?;
+        println!("Deleted root key {}", id);
+    }
+    publish(base_path, config)
+}
+
+/// Revokes every root key LND holds, root key 0 on the next restart
+pub fn rotate(base_path: &Path, config: &MacaroonsConfig) -> Result<(), anyhow::Error> {
+    revoke(base_path, config, &list_root_keys()?)
+}
+
+/// Root key IDs LND holds, with the macaroons the configurator knows were baked from them
+pub fn describe_root_keys(base_path: &Path) -> Result<BTreeMap<u64, Vec<String>>, anyhow::Error> {
+    let state = MacaroonState::load(base_path)?;
+    Ok(list_root_keys()?
+        .into_iter()
+        .map(|id| {
+            let owners = if id == 0 {
+                DEFAULT_MACAROONS
+                    .iter()
+                    .map(|n| format!("{}.macaroon", n))
+                    .chain(std::iter::once("health-check.macaroon".to_owned()))
+                    .collect()
+            } else {
+                state
+                    .profiles
+                    .iter()
+                    .filter(|(_, p)| p.root_key_id == id)
+                    .map(|(name, _)| format!("{}.macaroon", name))
+                    .collect()
+            };
+            (id, owners)
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct Permission {
+    entity: String,
+    action: String,
+}
+
+#[derive(Deserialize)]
+struct MethodPermissions {
+    #[serde(default)]
+    permissions: Vec<Permission>,
+}
+
+#[derive(Deserialize)]
+struct ListPermissions {
+    method_permissions: BTreeMap<String, MethodPermissions>,
+}
+
+/// The RPC methods a macaroon with `permissions` may call, according to LND
+fn effective_permissions(permissions: &[String]) -> Result<Vec<String>, anyhow::Error> {
+    Ok(rest::get::<ListPermissions>("/v1/macaroon/permissions")?
+        .method_permissions
+        .into_iter()
+        .filter(|(_, required)| {
+            required
+                .permissions
+                .iter()
+                .all(|p| permissions.contains(&format!("{}:{}", p.entity, p.action)))
+        })
+        .map(|(method, _)| method)
+        .collect())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Inspection {
+    pub file: String,
+    #[serde(flatten)]
+    pub macaroon: Macaroon,
+    /// RPC methods the macaroon may call, if LND could be asked
+    pub allowed_methods: Option<Vec<String>>,
+}
+
+/// Decodes the given macaroon files, or everything in the public dir
+pub fn inspect(base_path: &Path, files: &[String]) -> Result<Vec<Inspection>, anyhow::Error> {
+    let files = if files.is_empty() {
+        let mut public = Vec::new();
+        for entry in std::fs::read_dir(base_path.join(PUBLIC_DIR))? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("macaroon") {
+                public.push(path.display().to_string());
+            }
+        }
+        public.sort();
+        public
+    } else {
+        files.to_vec()
+    };
+    files
+        .into_iter()
+        .map(|file| {
+            let macaroon = Macaroon::decode(&std::fs::read(&file)?)
+                .map_err(|e| anyhow::anyhow!("{}: {}", file, e))?;
+            let allowed_methods = match effective_permissions(&macaroon.permissions) {
+                Ok(methods) => Some(methods),
+                Err(e) => {
+                    eprintln!("Could not list permissions: {}", e);
+                    None
+                }
+            };
+            Ok(Inspection {
+                file,
+                macaroon,
+                allowed_methods,
+            })
+        })
+        .collect()
+}
+
+/// The container IP of a dependent package
+fn resolve_package_ip(package_id: &str) -> Result<IpAddr, anyhow::Error> {
+    (format!("{}.embassy", package_id).as_str(), 0)
+        .to_socket_addrs()?
+        .map(|addr| addr.ip())
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("{}.embassy does not resolve", package_id))
+}
+
+/// Adds `time-before` and `ipaddr` caveats to a macaroon
+fn constrain(
+    base_path: &Path,
+    mac: &[u8],
+    timeout_secs: Option<u64>,
+    ip_address: Option<IpAddr>,
+) -> Result<Vec<u8>, anyhow::Error> {
+    if timeout_secs.is_none() && ip_address.is_none() {
+        return Ok(mac.to_vec());
+    }
+    let source = base_path.join("start9/constrain.macaroon.tmp");
+    let destination = base_path.join("start9/constrained.macaroon.tmp");
+    std::fs::write(&source, mac)?;
+    let timeout = timeout_secs.map(|t| t.to_string());
+    let ip_address = ip_address.map(|ip| ip.to_string());
+    let mut args = vec!["constrainmacaroon"];
+    if let Some(timeout) = &timeout {
+        args.extend_from_slice(&["--timeout", timeout]);
+    }
+    if let Some(ip_address) = &ip_address {
+        args.extend_from_slice(&["--ip_address", ip_address]);
+    }
+    let source_arg = source.display().to_string();
+    let destination_arg = destination.display().to_string();
+    args.extend_from_slice(&[&source_arg, &destination_arg]);
+    let res = lncli::run(&args).and_then(|_| Ok(std::fs::read(&destination)?));
+    let _ = std::fs::remove_file(source);
+    let _ = std::fs::remove_file(destination);
+    res
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), anyhow::Error> {
+    let tmp = path.with_extension("macaroon.tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Bakes every configured profile that isn't baked yet and publishes the configured macaroons to the public dir
+pub fn publish(base_path: &Path, config: &MacaroonsConfig) -> Result<(), anyhow::Error> {
+    let public = base_path.join(PUBLIC_DIR);
+    for name in DEFAULT_MACAROONS {
+        let file = format!("{}.macaroon", name);
+        if config.publish_default_macaroons {
+            let source = base_path.join(MACAROON_DIR).join(&file);
+            if source.exists() {
+                std::fs::copy(source, public.join(&file))?;
+            }
+        } else if std::fs::remove_file(public.join(&file)).is_ok() {
+            println!("Unpublished {}", file);
+        }
+    }
+
+    let mut state = MacaroonState::load(base_path)?;
+    let configured: BTreeMap<&str, &MacaroonProfile> = config
+        .profiles
+        .iter()
+        .map(|p| (p.name.as_str(), p))
+        .collect();
+    let removed: Vec<String> = state
+        .profiles
+        .keys()
+        .filter(|name| !configured.contains_key(name.as_str()))
+        .cloned()
+        .collect();
+    // a failing profile is logged and retried on the next pass, without holding up the others
+    for name in removed {
+        let baked = &state.profiles[&name];
+        let _ = std::fs::remove_file(public.join(format!("{}.macaroon", name)));
+        if let Err(e) = delete_root_key(baked.root_key_id) {
+            println!("Failed to revoke macaroon profile {}: {}", name, e);
+            continue;
+        }
+        println!("Revoked macaroon profile {}", name);
+        state.profiles.remove(&name);
+        state.store(base_path)?;
+    }
+    let mut taken = None;
+    for (name, profile) in configured {
+        if DEFAULT_MACAROONS.contains(&name) {
+            println!(
+                "Skipping macaroon profile {}: the name is taken by a macaroon LND creates",
+                name
+            );
+            continue;
+        }
+        let permissions = profile.permissions();
+        let path = public.join(format!("{}.macaroon", name));
+        let ip_address = match profile
+            .ip_lock
+            .as_deref()
+            .map(resolve_package_ip)
+            .transpose()
+        {
+            Ok(ip) => ip,
+            Err(e) => {
+                // never fall back to an unlocked macaroon
+                println!("Not publishing macaroon profile {}: {}", name, e);
+                continue;
+            }
+        };
+        let timeout_secs = profile.timeout.map(|hours| hours * 60 * 60);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let root_key_id = match state.profiles.get(name) {
+            None => match state.fresh_root_key_id(&mut taken) {
+                Ok(id) => id,
+                Err(e) => {
+                    println!("Failed to publish macaroon profile {}: {}", name, e);
+                    continue;
+                }
+            },
+            // permissions changed, so whatever was handed out before must stop working
+            Some(baked) if baked.permissions != permissions => {
+                let root_key_id = match state.fresh_root_key_id(&mut taken) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        println!("Failed to publish macaroon profile {}: {}", name, e);
+                        continue;
+                    }
+                };
+                let _ = std::fs::remove_file(&path);
+                if let Err(e) = delete_root_key(baked.root_key_id) {
+                    println!("Failed to revoke macaroon profile {}: {}", name, e);
+                    continue;
+                }
+                // forget the revoked key right away, so a failed bake below starts over
+                state.profiles.remove(name);
+                state.store(base_path)?;
+                root_key_id
+            }
+            Some(baked) => {
+                // re-issue once less than a quarter of the lifetime is left
+                let expiring = match (baked.expires_at, timeout_secs) {
+                    (Some(expires_at), Some(timeout)) => now + timeout / 4 >= expires_at,
+                    (None, None) => false,
+                    _ => true,
+                };
+                if path.exists() && baked.ip_address == ip_address && !expiring {
+                    continue;
+                }
+                baked.root_key_id
+            }
+        };
+        let published = bake(root_key_id, &permissions)
+            .and_then(|mac| constrain(base_path, &mac, timeout_secs, ip_address))
+            .and_then(|mac| write_atomic(&path, &mac));
+        if let Err(e) = published {
+            println!("Failed to publish macaroon profile {}: {}", name, e);
+            continue;
+        }
+        println!(
+            "Published macaroon profile {} (root key {})",
+            name, root_key_id
+        );
+        state.profiles.insert(
+            name.to_owned(),
+            BakedProfile {
+                root_key_id,
+                permissions,
+                expires_at: timeout_secs.map(|t| now + t),
+                ip_address,
+            },
+        );
+        state.store(base_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh_root_key_ids_skip_foreign_keys() {
+        let mut state = MacaroonState::default();
+        state.profiles.insert(
+            "btcpay".to_owned(),
+            BakedProfile {
+                root_key_id: 1,
+                permissions: Vec::new(),
+                expires_at: None,
+                ip_address: None,
+            },
+        );
+        // 0 is LND's, 2 and 4 were baked by other apps
+        let mut taken = Some([0, 1, 2, 4].iter().copied().collect());
+        assert_eq!(state.fresh_root_key_id(&mut taken).unwrap(), 3);
+        assert_eq!(state.fresh_root_key_id(&mut taken).unwrap(), 5);
+    }
+}
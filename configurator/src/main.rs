@@ -1,48 +1,34 @@
+mod epoch;
+mod lncli;
+mod lndconnect;
+mod macaroon;
+mod macaroons;
+mod net;
+mod properties;
+mod recovery;
+mod rest;
+mod watchtower;
+
 use base32::Alphabet;
 use bitcoincore_rpc::RpcApi;
 use rand::Rng;
 use serde_json::Value;
 use std::fs::File;
-use std::net::{Ipv4Addr, SocketAddr};
+use std::net::SocketAddr;
 use std::path::Path;
 use std::process::Command;
-use std::str::FromStr;
 use std::{
     io::{self, Read, Write},
     time::Duration,
 };
 
 use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+use watchtower::WatchtowerUri;
 
-fn parse_iface_ip(output: &str) -> Result<Option<&str>, anyhow::Error> {
-    let output = output.trim();
-    if output.is_empty() {
-        return Ok(None);
-    }
-    if let Some(ip) = output.split_ascii_whitespace().nth(3) {
-        Ok(Some(ip))
-    } else {
-        Err(anyhow::anyhow!("malformed output from `ip`"))
-    }
-}
-
-pub fn get_iface_ipv4_addr(iface: &str) -> Result<Option<Ipv4Addr>, anyhow::Error> {
-    Ok(parse_iface_ip(&String::from_utf8(
-        Command::new("ip")
-            .arg("-4")
-            .arg("-o")
-            .arg("addr")
-            .arg("show")
-            .arg(iface)
-            .output()?
-            .stdout,
-    )?)?
-    .map(|s| Ok::<_, anyhow::Error>(s.split("/").next().unwrap().parse()?))
-    .transpose()?)
-}
+const UNLOCK_ERROR_PATH: &str = "/root/.lnd/start9/unlock-error";
 
 fn pw_is_typeable(pw: &[u8]) -> bool {
-    pw.iter().all(|&byte| byte >= 32 && byte <= 126) // Space - ~
+    pw.iter().all(|byte| (32..=126).contains(byte)) // Space - ~
 }
 
 struct SkipNulls(Value);
@@ -86,6 +72,8 @@ struct Config {
     autopilot: AutoPilotConfig,
     watchtowers: WatchtowerConfig,
     advanced: AdvancedConfig,
+    #[serde(default)]
+    macaroons: macaroons::MacaroonsConfig,
     tor: TorConfig,
 }
 
@@ -94,6 +82,58 @@ struct Config {
 struct TorConfig {
     use_tor_only: bool,
     stream_isolation: bool,
+    #[serde(default)]
+    hybrid: Hybrid,
+}
+impl TorConfig {
+    /// `[Application Options]` rows that make the node reachable over clearnet.
+    /// The Tor listener on 9735 is always kept, since the Tor interface forwards to it.
+    fn hybrid_rows(&self) -> String {
+        let (external_hosts, p2p_port) = match &self.hybrid {
+            Hybrid::Disabled => return String::new(),
+            Hybrid::Enabled {
+                external_hosts,
+                p2p_port,
+            } => (external_hosts, *p2p_port),
+        };
+        // LND refuses to start with either combination, so fall back to Tor only
+        if self.use_tor_only || self.stream_isolation {
+            println!("WARNING: hybrid mode needs Tor to be skipped for clearnet peers and no stream isolation, ignoring it");
+            return String::new();
+        }
+        let mut rows = Vec::new();
+        if p2p_port != DEFAULT_P2P_PORT {
+            rows.push(format!("listen=0.0.0.0:{}", p2p_port));
+        }
+        for host in external_hosts {
+            match net::parse_address(host.trim(), p2p_port) {
+                // LND re-resolves externalhosts periodically, so dynamic DNS works
+                Ok((host @ net::Host::Dns(_), port)) => {
+                    rows.push(format!("externalhosts={}:{}", host, port))
+                }
+                Ok((host, port)) => rows.push(format!("externalip={}:{}", host, port)),
+                Err(e) => println!("WARNING: skipping external host {}: {}", host, e),
+            }
+        }
+        rows.join("\n")
+    }
+}
+
+const DEFAULT_P2P_PORT: u16 = 9735;
+
+#[derive(Default, Deserialize)]
+#[serde(tag = "enabled")]
+#[serde(rename_all = "kebab-case")]
+enum Hybrid {
+    #[default]
+    Disabled,
+    #[serde(rename_all = "kebab-case")]
+    Enabled {
+        /// static IPs or DNS names, with an optional port
+        #[serde(default)]
+        external_hosts: Vec<String>,
+        p2p_port: u16,
+    },
 }
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(tag = "enabled")]
@@ -102,7 +142,55 @@ enum WtClient {
     #[serde(rename_all = "kebab-case")]
     Disabled,
     #[serde(rename_all = "kebab-case")]
-    Enabled { add_watchtowers: Vec<String> },
+    Enabled {
+        add_watchtowers: Vec<String>,
+        /// sat/vbyte
+        #[serde(default)]
+        sweep_fee_rate: Option<u64>,
+        /// blocks
+        #[serde(default)]
+        session_close_range: Option<u32>,
+        #[serde(default)]
+        max_tasks_in_mem_queue: Option<u64>,
+        #[serde(default)]
+        max_updates: Option<u16>,
+    },
+}
+impl WtClient {
+    /// `[wtclient]` rows for the policy options that are set. Session types
+    /// (legacy, anchor, taproot) are chosen by LND per channel, so there is
+    /// nothing to configure for them.
+    fn policy_rows(&self) -> String {
+        let mut rows = Vec::new();
+        if let WtClient::Enabled {
+            sweep_fee_rate,
+            session_close_range,
+            max_tasks_in_mem_queue,
+            max_updates,
+            ..
+        } = self
+        {
+            if let Some(sweep_fee_rate) = sweep_fee_rate {
+                rows.push(format!("wtclient.sweep-fee-rate={}", sweep_fee_rate));
+            }
+            if let Some(session_close_range) = session_close_range {
+                rows.push(format!(
+                    "wtclient.session-close-range={}",
+                    session_close_range
+                ));
+            }
+            if let Some(max_tasks_in_mem_queue) = max_tasks_in_mem_queue {
+                rows.push(format!(
+                    "wtclient.max-tasks-in-mem-queue={}",
+                    max_tasks_in_mem_queue
+                ));
+            }
+            if let Some(max_updates) = max_updates {
+                rows.push(format!("wtclient.max-updates={}", max_updates));
+            }
+        }
+        rows.join("\n")
+    }
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
@@ -110,14 +198,67 @@ enum WtClient {
 #[serde(rename_all = "kebab-case")]
 struct WatchtowerConfig {
     wt_server: bool,
+    #[serde(default)]
+    wt_server_settings: WtServerSettings,
     wt_client: WtClient,
 }
 
-#[derive(Deserialize, Serialize)]
-struct TowerInfo {
-    pubkey: String,
-    listeners: Vec<String>,
-    uris: Vec<String>,
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct WtServerSettings {
+    listen_port: u16,
+    /// clearnet or onion hosts, with an optional port
+    #[serde(default)]
+    external_hosts: Vec<String>,
+    /// seconds
+    #[serde(default)]
+    read_timeout: Option<u64>,
+    /// seconds
+    #[serde(default)]
+    write_timeout: Option<u64>,
+    /// relative to /root/.lnd
+    #[serde(default)]
+    tower_dir: Option<String>,
+}
+impl Default for WtServerSettings {
+    fn default() -> Self {
+        WtServerSettings {
+            listen_port: watchtower::DEFAULT_PORT,
+            external_hosts: Vec::new(),
+            read_timeout: None,
+            write_timeout: None,
+            tower_dir: None,
+        }
+    }
+}
+impl WtServerSettings {
+    /// `[watchtower]` rows beyond the Tor listener and onion address, which
+    /// always stay on 9911 because that is what the Tor interface forwards to
+    fn rows(&self) -> String {
+        let mut rows = Vec::new();
+        if self.listen_port != watchtower::DEFAULT_PORT {
+            rows.push(format!("watchtower.listen=0.0.0.0:{}", self.listen_port));
+        }
+        for host in &self.external_hosts {
+            match net::parse_address(host.trim(), self.listen_port) {
+                Ok((host, port)) => rows.push(format!("watchtower.externalip={}:{}", host, port)),
+                Err(e) => println!("WARNING: skipping watchtower external host {}: {}", host, e),
+            }
+        }
+        if let Some(read_timeout) = self.read_timeout {
+            rows.push(format!("watchtower.readtimeout={}s", read_timeout));
+        }
+        if let Some(write_timeout) = self.write_timeout {
+            rows.push(format!("watchtower.writetimeout={}s", write_timeout));
+        }
+        if let Some(tower_dir) = &self.tower_dir {
+            rows.push(format!(
+                "watchtower.towerdir={}",
+                Path::new("/root/.lnd").join(tower_dir).display()
+            ));
+        }
+        rows.join("\n")
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -193,9 +334,19 @@ struct AdvancedConfig {
     protocol_simple_taproot_chans: bool,
     gc_canceled_invoices_on_startup: bool,
     allow_circular_route: bool,
+    /// interface whose addresses the gRPC and REST APIs listen on
+    #[serde(default)]
+    rpc_interface: Option<String>,
     bitcoin: BitcoinChannelConfig,
     sweeper: SweeperConfig,
 }
+impl AdvancedConfig {
+    fn rpc_interface(&self) -> &str {
+        self.rpc_interface
+            .as_deref()
+            .unwrap_or(net::DEFAULT_INTERFACE)
+    }
+}
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Properties {
@@ -205,6 +356,7 @@ pub struct Properties {
 
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum Data {
     LND {
         #[serde(rename = "LND Sync Height")]
@@ -223,6 +375,12 @@ pub enum Data {
         node_alias: Property<String>,
         #[serde(rename = "Node Id")]
         node_id: Property<String>,
+        #[serde(rename = "LND Aezeed Cipher Seed")]
+        cipher_seed: Property<String>,
+        #[serde(rename = "Tower Server", skip_serializing_if = "Option::is_none")]
+        tower_server: Option<Property<String>>,
+        #[serde(rename = "Tower Server URIs", skip_serializing_if = "Option::is_none")]
+        tower_server_uris: Option<Property<String>>,
     },
     NotReady {
         #[serde(rename = "Not Ready")]
@@ -288,6 +446,12 @@ pub fn local_port_available(port: u16) -> Result<bool, anyhow::Error> {
     }
 }
 
+fn generate_password() -> io::Result<Vec<u8>> {
+    let mut buf = [0; 16];
+    File::open("/dev/random")?.read_exact(&mut buf)?;
+    Ok(base32::encode(Alphabet::RFC4648 { padding: false }, &buf).into_bytes())
+}
+
 fn save_to_file(cipher_seed_mnemonic: &[String], file_path: &str) -> io::Result<()> {
     let mut file = File::create(file_path)?;
     for (i, word) in cipher_seed_mnemonic.iter().enumerate() {
@@ -296,34 +460,150 @@ fn save_to_file(cipher_seed_mnemonic: &[String], file_path: &str) -> io::Result<
     Ok(())
 }
 
-struct WatchtowerUri {
-    pubkey: String,
-    address: String,
+/// Parses the configured tower URIs, skipping invalid ones
+fn configured_watchtowers(uris: &[String]) -> Vec<WatchtowerUri> {
+    uris.iter()
+        .filter_map(|uri| match uri.parse() {
+            Ok(uri) => Some(uri),
+            Err(e) => {
+                eprintln!("WARNING: skipping watchtower {}: {}", uri, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Makes the watchtower client use exactly the configured towers, stopping at
+/// the first failure. With `dry_run` the planned changes are only returned.
+fn reconcile_watchtowers(
+    configured: &[WatchtowerUri],
+    dry_run: bool,
+) -> Result<Vec<watchtower::Change>, anyhow::Error> {
+    let changes = watchtower::plan(configured, &watchtower::towers()?);
+    if !dry_run {
+        for change in &changes {
+            change.apply()?;
+            println!("Watchtower client: {}", change);
+        }
+    }
+    Ok(changes)
 }
-impl FromStr for WatchtowerUri {
-    type Err = anyhow::Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut split_input = s.split("@");
-        let pubkey = match split_input.next() {
-            Some(x) => x.to_string(),
-            None => anyhow::bail!("Couldn't parse the pubkey from watchtower URI"),
-        };
-        let address = match split_input.next() {
-            Some(x) => x.to_string(),
-            None => anyhow::bail!("Couldn't parse the address from watchtower URI"),
-        };
-        Ok(WatchtowerUri { pubkey, address })
+/// `configurator watchtowers [--dry-run]`
+fn watchtowers_command(args: impl Iterator<Item = String>) -> Result<(), anyhow::Error> {
+    let mut dry_run = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            _ => anyhow::bail!("usage: configurator watchtowers [--dry-run]"),
+        }
+    }
+    let config: Config = serde_yaml::from_reader(File::open("/root/.lnd/start9/config.yaml")?)?;
+    let configured = match &config.watchtowers.wt_client {
+        WtClient::Disabled => anyhow::bail!("Watchtower Client is disabled"),
+        WtClient::Enabled {
+            add_watchtowers, ..
+        } => configured_watchtowers(add_watchtowers),
+    };
+    let changes = reconcile_watchtowers(&configured, dry_run)?;
+    println!("{}", serde_json::to_string_pretty(&changes)?);
+    Ok(())
+}
+
+/// `configurator macaroons list|revoke <root key id>...|rotate`
+fn macaroons_command(mut args: impl Iterator<Item = String>) -> Result<(), anyhow::Error> {
+    let base_path = Path::new("/root/.lnd");
+    let config = || -> Result<Config, anyhow::Error> {
+        Ok(serde_yaml::from_reader(File::open(
+            "/root/.lnd/start9/config.yaml",
+        )?)?)
+    };
+    match args.next().as_deref() {
+        Some("list") => print!(
+            "{}",
+            serde_yaml::to_string(&macaroons::describe_root_keys(base_path)?)?
+        ),
+        Some("revoke") => {
+            let ids = args.map(|id| id.parse()).collect::<Result<Vec<u64>, _>>()?;
+            if ids.is_empty() {
+                anyhow::bail!("revoke needs at least one root key id");
+            }
+            macaroons::revoke(base_path, &config()?.macaroons, &ids)?;
+        }
+        Some("rotate") => macaroons::rotate(base_path, &config()?.macaroons)?,
+        _ => anyhow::bail!("usage: configurator macaroons list|revoke <root key id>...|rotate"),
+    }
+    Ok(())
+}
+
+/// `configurator lndconnect [--rest] [--lan] [--no-cert] [--macaroon <name>]`
+fn lndconnect_uri(
+    args: impl Iterator<Item = String>,
+) -> Result<lndconnect::LndConnect, anyhow::Error> {
+    let mut rest = false;
+    let mut lan = false;
+    let mut cert = true;
+    let mut macaroon = "admin".to_owned();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rest" => rest = true,
+            "--lan" => lan = true,
+            "--no-cert" => cert = false,
+            "--macaroon" => {
+                macaroon = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--macaroon needs a name"))?
+            }
+            other => anyhow::bail!("Unknown lndconnect option: {}", other),
+        }
     }
+    let config: Config = serde_yaml::from_reader(File::open("/root/.lnd/start9/config.yaml")?)?;
+    let host = if lan {
+        let iface = config.advanced.rpc_interface();
+        net::iface_ipv4_addr(iface)?
+            .ok_or_else(|| anyhow::anyhow!("{} has no IPv4 address", iface))?
+            .to_string()
+    } else {
+        config.control_tor_address
+    };
+    let macaroon = std::fs::read(format!(
+        "/root/.lnd/data/chain/bitcoin/mainnet/{}.macaroon",
+        macaroon
+    ))?;
+    let uri = lndconnect::LndConnect::new(host, if rest { 8080 } else { 10009 }, macaroon);
+    Ok(if cert {
+        uri.with_cert(lndconnect::cert_der(&std::fs::read(
+            "/root/.lnd/tls.cert",
+        )?)?)
+    } else {
+        uri
+    })
 }
 
 fn main() -> Result<(), anyhow::Error> {
+    match std::env::args().nth(1).as_deref() {
+        Some("properties") => return properties::write(Path::new("/root/.lnd")),
+        Some("macaroons") => return macaroons_command(std::env::args().skip(2)),
+        Some("watchtowers") => return watchtowers_command(std::env::args().skip(2)),
+        Some("inspect-macaroon") => {
+            let files: Vec<String> = std::env::args().skip(2).collect();
+            let inspections = macaroons::inspect(Path::new("/root/.lnd"), &files)?;
+            println!("{}", serde_json::to_string_pretty(&inspections)?);
+            return Ok(());
+        }
+        Some("lndconnect") => {
+            let uri = lndconnect_uri(std::env::args().skip(2))?;
+            println!("{}", uri);
+            return Ok(());
+        }
+        _ => (),
+    }
     while !Path::new("/root/.lnd/start9/config.yaml").exists() {
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
     let config: Config = serde_yaml::from_reader(File::open("/root/.lnd/start9/config.yaml")?)?;
     let alias = get_alias(&config)?;
-    let control_tor_address = config.control_tor_address;
     let watchtower_tor_address = config.watchtower_tor_address;
     let peer_tor_address = config.peer_tor_address;
 
@@ -334,10 +614,7 @@ fn main() -> Result<(), anyhow::Error> {
     println!("alias = {:?}", alias);
     let mut outfile = File::create("/root/.lnd/lnd.conf")?;
 
-    let bitcoind_selected = match &config.bitcoind {
-        BitcoinCoreConfig::None => false,
-        _ => true,
-    };
+    let bitcoind_selected = !matches!(config.bitcoind, BitcoinCoreConfig::None);
 
     println!("bitcoind_selected = {}", bitcoind_selected);
 
@@ -363,7 +640,7 @@ fn main() -> Result<(), anyhow::Error> {
     };
 
     let rpc_info = &BitcoindRpcInfo {
-        host: &bitcoind_rpc_host,
+        host: bitcoind_rpc_host,
         port: bitcoind_rpc_port,
         user: &bitcoind_rpc_user,
         pass: &bitcoind_rpc_pass,
@@ -386,15 +663,36 @@ fn main() -> Result<(), anyhow::Error> {
     let use_neutrino = !(bitcoind_selected && bitcoin_synced);
     println!("use_neutrino = {}", use_neutrino);
 
-    let container_ip = get_iface_ipv4_addr("eth0").unwrap_or_else(|e| {
+    let rpc_addrs = net::iface_addrs(config.advanced.rpc_interface()).unwrap_or_else(|e| {
         eprintln!("{e}");
-        None
+        Vec::new()
     });
+    if rpc_addrs.is_empty() {
+        println!(
+            "WARNING: {} has no usable addresses, the APIs will listen on all interfaces",
+            config.advanced.rpc_interface()
+        );
+    }
+    let rpc_listen_rows = if rpc_addrs.is_empty() {
+        "rpclisten=0.0.0.0:10009\nrestlisten=0.0.0.0:8080".to_owned()
+    } else {
+        rpc_addrs
+            .iter()
+            .map(|ip| {
+                format!(
+                    "rpclisten={}\nrestlisten={}",
+                    SocketAddr::new(*ip, 10009),
+                    SocketAddr::new(*ip, 8080)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
 
     write!(
         outfile,
         include_str!("lnd.conf.template"),
-        container_ip = container_ip.unwrap_or_else(|| [0, 0, 0, 0].into()),
+        rpc_listen_rows = rpc_listen_rows,
         peer_tor_address = peer_tor_address,
         watchtower_tor_address = watchtower_tor_address,
         payments_expiration_grace_period = config.advanced.payments_expiration_grace_period,
@@ -464,11 +762,11 @@ fn main() -> Result<(), anyhow::Error> {
         db_bolt_db_timeout = config.advanced.db_bolt_db_timeout,
         tor_enable_clearnet = !config.tor.use_tor_only,
         tor_stream_isolation = config.tor.stream_isolation,
+        hybrid_rows = config.tor.hybrid_rows(),
         wt_server = config.watchtowers.wt_server,
-        wt_client = match config.watchtowers.wt_client {
-            WtClient::Disabled => false,
-            _ => true,
-        }
+        wt_server_rows = config.watchtowers.wt_server_settings.rows(),
+        wt_client = !matches!(config.watchtowers.wt_client, WtClient::Disabled),
+        wt_client_policy_rows = config.watchtowers.wt_client.policy_rows(),
     )?;
     let public_path = Path::new("/root/.lnd/public");
     // Create public directory to make accessible to dependents through the bindmounts interface
@@ -483,13 +781,36 @@ fn main() -> Result<(), anyhow::Error> {
     )?;
     std::fs::rename("/root/.lnd/.backupignore.tmp", "/root/.lnd/.backupignore")?;
 
+    // channel.db is never backed up (see .backupignore), so a restore always
+    // recovers channels through channel.backup. Warn about channels it cannot know.
+    if is_restore(Path::new("/root/.lnd")) {
+        println!("checking the restored channel backup against the latest node epoch...");
+        match epoch::check_restore(Path::new("/root/.lnd")) {
+            Ok(epoch::RestoreCheck::Stale(channels)) => println!(
+                "WARNING: {} channel(s) were opened after this backup was taken and cannot be recovered from it: {}. Ask those peers to force close.",
+                channels.len(),
+                channels.join(", ")
+            ),
+            Ok(epoch::RestoreCheck::Current) => {
+                println!("The restored channel backup covers every known channel.")
+            }
+            Ok(epoch::RestoreCheck::Unknown) => {
+                println!("No node epoch to compare the restored channel backup against.")
+            }
+            Err(e) => println!("Error checking the restored node epoch: {:?}", e),
+        }
+    }
+
     // background configurator so lnd can start
     #[cfg(target_os = "linux")]
     nix::unistd::daemon(true, true)?;
-    let container_ip = container_ip.unwrap_or_else(|| [127, 0, 0, 1].into());
-    println!("checking port 10009 on {container_ip} (gRPC control port)...");
+    let rpc_ip = rpc_addrs
+        .first()
+        .copied()
+        .unwrap_or_else(|| [127, 0, 0, 1].into());
+    println!("checking port 10009 on {rpc_ip} (gRPC control port)...");
     loop {
-        if let Ok(_) = std::net::TcpStream::connect(SocketAddr::from((container_ip, 10009))) {
+        if std::net::TcpStream::connect(SocketAddr::new(rpc_ip, 10009)).is_ok() {
             break;
         } else {
             std::thread::sleep(std::time::Duration::from_secs(1));
@@ -519,13 +840,67 @@ fn main() -> Result<(), anyhow::Error> {
     }?;
 
     println!("unlocking wallet...");
-    if Path::new("/root/.lnd/pwd.dat").exists() {
+    // a failure from a previous start no longer applies
+    if std::fs::remove_file(UNLOCK_ERROR_PATH).is_ok() {
+        println!("Cleared previous wallet unlock error.");
+    }
+    // the request is only removed once the wallet exists, so this resumes an interrupted recovery
+    let recovered = match recovery::pending_request(Path::new("/root/.lnd"))? {
+        Some(request) => {
+            println!("Detected disaster recovery request. Recovering wallet from seed...");
+            // persisted before the wallet is created, so a crash can never leave a wallet without its password
+            let password_bytes = if Path::new("/root/.lnd/pwd.dat").exists() {
+                std::fs::read("/root/.lnd/pwd.dat")?
+            } else {
+                let password_bytes = generate_password()?;
+                std::fs::write("/root/.lnd/pwd.dat.tmp", &password_bytes)?;
+                std::fs::rename("/root/.lnd/pwd.dat.tmp", "/root/.lnd/pwd.dat")?;
+                password_bytes
+            };
+            let file_path = "/root/.lnd/start9/cipherSeedMnemonic.txt";
+            if let Err(err) = save_to_file(&request.cipher_seed_mnemonic, file_path) {
+                eprintln!("Failed to save the CipherSeedMnemonic: {}", err);
+            }
+            let recovery_window = config
+                .advanced
+                .recovery_window
+                .unwrap_or(recovery::DEFAULT_RECOVERY_WINDOW);
+            let created = recovery::init_wallet(
+                Path::new("/root/.lnd"),
+                &request,
+                &password_bytes,
+                recovery_window,
+            )?;
+            if created {
+                println!(
+                    "Wallet created. Rescanning with a recovery window of {} addresses.",
+                    recovery_window
+                );
+            } else {
+                println!("Wallet was already created before a restart. Unlocking it...");
+            }
+            created
+        }
+        None => false,
+    };
+    if recovered {
+        // initwallet leaves the wallet unlocked
+    } else if Path::new("/root/.lnd/pwd.dat").exists() {
+        let resume_window = recovery::resume_window(Path::new("/root/.lnd"))?;
         let password_bytes = std::fs::read("/root/.lnd/pwd.dat")?;
         let pw_typeable = pw_is_typeable(&password_bytes);
-        let status;
-        if !pw_typeable {
-            let base_32_pw = base32::encode(Alphabet::RFC4648 { padding: false }, &password_bytes);
-            status = {
+        // root key 0 can only be replaced while unlocking, through changepassword
+        let rotate_root_key = macaroons::default_rotation_requested(Path::new("/root/.lnd"));
+        let status = if !pw_typeable || rotate_root_key {
+            let base_32_pw = if pw_typeable {
+                String::from_utf8(password_bytes.clone())?
+            } else {
+                base32::encode(Alphabet::RFC4648 { padding: false }, &password_bytes)
+            };
+            if rotate_root_key {
+                macaroons::clear_default_macaroons(Path::new("/root/.lnd"))?;
+            }
+            {
                 use std::process;
                 let mut res;
                 let stat;
@@ -542,6 +917,7 @@ fn main() -> Result<(), anyhow::Error> {
                         .arg(serde_json::to_string(&SkipNulls(serde_json::json!({
                             "current_password": base64::encode(&password_bytes),
                             "new_password": base64::encode(&base_32_pw),
+                            "new_macaroon_root_key": rotate_root_key,
                         })))?)
                         .stdin(process::Stdio::piped())
                         .stdout(process::Stdio::piped())
@@ -560,9 +936,18 @@ fn main() -> Result<(), anyhow::Error> {
                         Some(o) => match o.get("message") {
                             None => {
                                 stat = Ok(output);
-                                std::fs::write("/root/.lnd/new_pwd.dat", &base_32_pw)?;
-                                std::fs::rename("/root/.lnd/new_pwd.dat", "/root/.lnd/pwd.dat")?;
-                                println!("Wallet password successfully converted to base32");
+                                if !pw_typeable {
+                                    std::fs::write("/root/.lnd/new_pwd.dat", &base_32_pw)?;
+                                    std::fs::rename(
+                                        "/root/.lnd/new_pwd.dat",
+                                        "/root/.lnd/pwd.dat",
+                                    )?;
+                                    println!("Wallet password successfully converted to base32");
+                                }
+                                if rotate_root_key {
+                                    macaroons::finish_default_rotation(Path::new("/root/.lnd"))?;
+                                    println!("Rotated macaroon root key 0");
+                                }
                                 break;
                             }
                             Some(v) => match v.as_str() {
@@ -586,9 +971,9 @@ fn main() -> Result<(), anyhow::Error> {
                     }
                 }
                 stat
-            };
+            }
         } else {
-            status = {
+            {
                 use std::process;
                 let mut res;
                 let stat;
@@ -604,7 +989,7 @@ fn main() -> Result<(), anyhow::Error> {
                         .arg("-d")
                         .arg(serde_json::to_string(&SkipNulls(serde_json::json!({
                             "wallet_password": base64::encode(&password_bytes),
-                            "recovery_window": config.advanced.recovery_window,
+                            "recovery_window": resume_window.or(config.advanced.recovery_window),
                         })))?)
                         .stdin(process::Stdio::piped())
                         .stdout(process::Stdio::piped())
@@ -646,11 +1031,13 @@ fn main() -> Result<(), anyhow::Error> {
                     }
                 }
                 stat
-            };
-        }
+            }
+        };
         match status {
             Err(e) => {
                 eprintln!("{}", e);
+                // lets the health checks tell a failed unlock apart from one that hasn't happened yet
+                std::fs::write(UNLOCK_ERROR_PATH, e.to_string())?;
                 return Err(anyhow::anyhow!("Error unlocking wallet. Exiting."));
             }
             // wallet unlocking has to happen while LND running (encrypted on disk) creds are stored in separate place on disk (pwd.dat in our case - in data volume)
@@ -690,11 +1077,7 @@ fn main() -> Result<(), anyhow::Error> {
     } else {
         let mut cipher_seed_created = false;
         println!("creating password data");
-        let password_bytes = {
-            let mut buf = [0; 16];
-            File::open("/dev/random")?.read_exact(&mut buf)?;
-            base32::encode(Alphabet::RFC4648 { padding: false }, &buf).into_bytes()
-        };
+        let password_bytes = generate_password()?;
         let file_path = "/root/.lnd/start9/cipherSeedMnemonic.txt";
 
         while !cipher_seed_created {
@@ -754,150 +1137,71 @@ fn main() -> Result<(), anyhow::Error> {
         }
     }
 
-    println!("copying macaroon to public dir...");
+    println!("publishing macaroons to public dir...");
     while !Path::new("/root/.lnd/data/chain/bitcoin/mainnet/admin.macaroon").exists() {
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
-    for macaroon in std::fs::read_dir("/root/.lnd/data/chain/bitcoin/mainnet")? {
-        let macaroon = macaroon?;
-        if macaroon.path().extension().and_then(|s| s.to_str()) == Some("macaroon") {
-            std::fs::copy(
-                macaroon.path(),
-                public_path.join(macaroon.path().file_name().unwrap()),
-            )?;
-        }
+    if let Err(e) = macaroons::publish(Path::new("/root/.lnd"), &config.macaroons) {
+        println!("Error publishing macaroons: {:?}", e);
     }
 
-    match config.watchtowers.wt_server {
-        false => {
-            println!("Watchtower Server disabled");
-            if let Err(_) = std::fs::remove_file("/root/.lnd/start9/towerServerUrl") {
-                println!("The towerServerUrl file does not exist or cannot be deleted.");
-            } else {
-                println!("The towerServerUrl file has been deleted successfully.");
-            }
-        }
-        true => loop {
-            let output = Command::new("lncli")
-                .arg("--rpcserver=lnd.embassy")
-                .arg("tower")
-                .arg("info")
-                .output();
-            match output {
-                Ok(output) if output.status.success() => {
-                    println!("Tower server {:?} started", &output);
-                    let tower_info_response = String::from_utf8_lossy(&output.stdout);
-                    let tower_server: TowerInfo = serde_json::from_str(&tower_info_response)
-                        .expect("Failed to parse Tower Info JSON response");
-                    let result =
-                        std::fs::write("/root/.lnd/start9/towerServerUrl", &tower_server.uris[0]);
-                    match result {
-                        Ok(_) => {
-                            println!("Tower {} written towerServerUrl", &tower_server.uris[0]);
-                        }
-                        Err(err) => {
-                            println!("Error writing Tower server to Properties: {}", err);
-                        }
-                    }
-                    break;
-                }
-                Ok(output) => {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    println!("Failed to retreive tower info with error: {}", stderr);
-                    std::thread::sleep(Duration::from_secs(10));
-                }
-                Err(_) => {
-                    println!("Error running the command: lncli --rpcserver=lnd.embassy tower info");
-                    std::thread::sleep(Duration::from_secs(10));
-                }
-            }
-        },
+    if config.watchtowers.wt_server {
+        watchtower::spawn_tower_info(Path::new("/root/.lnd").to_owned());
+    } else {
+        println!("Watchtower Server disabled");
+        watchtower::clear_tower_info(Path::new("/root/.lnd"));
     }
 
-    if true {
-        match config.watchtowers.wt_client {
-            WtClient::Disabled => {
-                println!("Watchtower Client Disabled");
-            }
-            WtClient::Enabled { add_watchtowers } => {
-                for watchtower_uri in add_watchtowers.iter() {
-                    let parsed_watchtower_uri: WatchtowerUri = watchtower_uri.parse()?;
-                    loop {
-                        println!("Configuring Watchtower for {}... ", alias);
-                        println!(
-                            "pubkey: {} || host: {}",
-                            &parsed_watchtower_uri.pubkey, &parsed_watchtower_uri.address
-                        );
-                        let output = Command::new("lncli")
-                            .arg("--rpcserver=lnd.embassy")
-                            .arg("wtclient")
-                            .arg("add")
-                            .arg(&watchtower_uri)
-                            .output();
-                        println!("The lncli command ran for {}", &watchtower_uri);
-                        match output {
-                            Ok(output) if output.status.success() => {
-                                println!("Added watchtower {}.", &watchtower_uri);
-                                break;
-                            }
-                            Ok(output) => {
-                                let stderr = String::from_utf8_lossy(&output.stderr);
-                                println!(
-                                    "Failed to add watchtower {} with error: {}",
-                                    &watchtower_uri, stderr
-                                );
-                                std::thread::sleep(Duration::from_secs(10));
-                            }
-                            Err(_) => {
-                                println!("Error running the command: lncli --rpcserver=lnd.embassy wtclient add {}.", &watchtower_uri);
-                                std::thread::sleep(Duration::from_secs(10));
-                            }
-                        }
-                    }
-                }
-            }
+    match &config.watchtowers.wt_client {
+        WtClient::Disabled => {
+            println!("Watchtower Client Disabled");
         }
-    };
+        WtClient::Enabled {
+            add_watchtowers, ..
+        } => {
+            println!("Configuring Watchtowers for {}... ", alias);
+            watchtower::spawn_registration(
+                Path::new("/root/.lnd").to_owned(),
+                configured_watchtowers(add_watchtowers),
+            );
+        }
+    }
 
-    if bitcoind_selected {
-        println!("looping forever to see if we need to switch backends...");
-        loop {
-            let bitcoin_synced = match bitcoin_is_synced(rpc_info) {
-                Ok(bs) => bs,
-                Err(e) => {
-                    println!("Error checking whether bitcoin is synced: {:?}", e);
-                    std::thread::sleep(std::time::Duration::from_secs(60));
-                    continue;
-                }
-            };
-            if use_neutrino == bitcoin_synced {
-                if bitcoin_synced {
-                    println!("Detected bitcoind end of IBD. Restarting to turn off Neutrino.");
-                } else {
-                    println!("Detected bitcoind in IBD. Restarting to turn on Neutrino.");
+    println!("looping forever to record the node epoch and see if we need to switch backends...");
+    loop {
+        if let Err(e) = epoch::record(Path::new("/root/.lnd")) {
+            println!("Error recording node epoch: {:?}", e);
+        }
+        if let Err(e) = recovery::step(Path::new("/root/.lnd")) {
+            println!("Error tracking disaster recovery: {:?}", e);
+        }
+        if let Err(e) = macaroons::publish(Path::new("/root/.lnd"), &config.macaroons) {
+            println!("Error publishing macaroons: {:?}", e);
+        }
+        // baking fails until LND is fully started, so keep trying until the file exists
+        if let Err(e) = macaroons::ensure_health_check_macaroon(Path::new("/root/.lnd")) {
+            println!("Error baking health check macaroon: {:?}", e);
+        }
+        if let Err(e) = properties::write(Path::new("/root/.lnd")) {
+            println!("Error writing properties: {:?}", e);
+        }
+        if bitcoind_selected {
+            match bitcoin_is_synced(rpc_info) {
+                Ok(bitcoin_synced) if use_neutrino == bitcoin_synced => {
+                    if bitcoin_synced {
+                        println!("Detected bitcoind end of IBD. Restarting to turn off Neutrino.");
+                    } else {
+                        println!("Detected bitcoind in IBD. Restarting to turn on Neutrino.");
+                    }
+                    let parent_process_id = nix::unistd::getppid();
+                    nix::sys::signal::kill(parent_process_id, nix::sys::signal::Signal::SIGTERM)?;
                 }
-                let parent_process_id = nix::unistd::getppid();
-                nix::sys::signal::kill(parent_process_id, nix::sys::signal::Signal::SIGTERM)?;
+                Ok(_) => (),
+                Err(e) => println!("Error checking whether bitcoin is synced: {:?}", e),
             }
-            std::thread::sleep(std::time::Duration::from_secs(60));
         }
-    };
-
-    println!("configurator exiting...");
-
-    Ok(())
-}
-
-#[derive(Serialize, Deserialize)]
-struct JsonRpc1Res {
-    result: serde_json::Value,
-    error: Option<BitcoindError>,
-    id: serde_json::Value,
-}
-#[derive(Debug, Serialize, Deserialize)]
-struct BitcoindError {
-    code: i32,
-    message: String,
+        std::thread::sleep(std::time::Duration::from_secs(60));
+    }
 }
 
 #[derive(Debug)]
@@ -910,7 +1214,7 @@ struct BitcoindRpcInfo<'a> {
 
 fn bitcoin_rpc_is_ready(rpc_info: &BitcoindRpcInfo) -> Result<bool, anyhow::Error> {
     let rpc_client = bitcoincore_rpc::Client::new(
-        &*format!("http://{}:{}", rpc_info.host, rpc_info.port),
+        &format!("http://{}:{}", rpc_info.host, rpc_info.port),
         bitcoincore_rpc::Auth::UserPass(rpc_info.user.to_owned(), rpc_info.pass.to_owned()),
     )?;
     Ok(rpc_client.get_best_block_hash().is_ok())
@@ -918,7 +1222,7 @@ fn bitcoin_rpc_is_ready(rpc_info: &BitcoindRpcInfo) -> Result<bool, anyhow::Erro
 
 fn bitcoin_is_synced(rpc_info: &BitcoindRpcInfo) -> Result<bool, anyhow::Error> {
     let rpc_client = bitcoincore_rpc::Client::new(
-        &*format!("http://{}:{}", rpc_info.host, rpc_info.port),
+        &format!("http://{}:{}", rpc_info.host, rpc_info.port),
         bitcoincore_rpc::Auth::UserPass(rpc_info.user.to_owned(), rpc_info.pass.to_owned()),
     )?;
     match rpc_client.get_blockchain_info() {
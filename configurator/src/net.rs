@@ -0,0 +1,146 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use nix::ifaddrs::getifaddrs;
+use nix::sys::socket::SockAddr;
+
+pub const DEFAULT_INTERFACE: &str = "eth0";
+
+/// Every address of `iface` that LND can bind to, IPv4 first. Link-local IPv6
+/// addresses are left out because they cannot be bound without a zone.
+pub fn iface_addrs(iface: &str) -> Result<Vec<IpAddr>, anyhow::Error> {
+    let mut addrs: Vec<IpAddr> = getifaddrs()?
+        .filter(|ifaddr| ifaddr.interface_name == iface)
+        .filter_map(|ifaddr| match ifaddr.address {
+            Some(SockAddr::Inet(addr)) => Some(addr.ip().to_std()),
+            _ => None,
+        })
+        .filter(|ip| match ip {
+            IpAddr::V4(_) => true,
+            IpAddr::V6(ip) => ip.segments()[0] & 0xffc0 != 0xfe80,
+        })
+        .collect();
+    addrs.sort_by_key(|ip| ip.is_ipv6());
+    addrs.dedup();
+    Ok(addrs)
+}
+
+pub fn iface_ipv4_addr(iface: &str) -> Result<Option<Ipv4Addr>, anyhow::Error> {
+    Ok(iface_addrs(iface)?.into_iter().find_map(|ip| match ip {
+        IpAddr::V4(ip) => Some(ip),
+        IpAddr::V6(_) => None,
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Host {
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Dns(String),
+    /// v3 onion address, including the `.onion` suffix
+    Onion(String),
+}
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Ipv4(ip) => write!(f, "{}", ip),
+            Host::Ipv6(ip) => write!(f, "[{}]", ip),
+            Host::Dns(name) | Host::Onion(name) => write!(f, "{}", name),
+        }
+    }
+}
+impl FromStr for Host {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(ip) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            return Ok(Host::Ipv6(ip.parse().map_err(|_| {
+                anyhow::anyhow!("{} is not a valid IPv6 address", ip)
+            })?));
+        }
+        if s.contains(':') {
+            anyhow::bail!("IPv6 address {} must be enclosed in brackets", s);
+        }
+        if let Ok(ip) = s.parse() {
+            return Ok(Host::Ipv4(ip));
+        }
+        let name = s.to_ascii_lowercase();
+        if let Some(onion) = name.strip_suffix(".onion") {
+            return parse_onion_v3(onion).map(|_| Host::Onion(name));
+        }
+        if name.len() > 253 {
+            anyhow::bail!("host name {} is longer than 253 characters", s);
+        }
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                anyhow::bail!("host name {} has an empty or overlong label", s);
+            }
+            if label.starts_with('-')
+                || label.ends_with('-')
+                || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                anyhow::bail!("host name {} contains an invalid label {:?}", s, label);
+            }
+        }
+        if name.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            anyhow::bail!("{} is not a valid IPv4 address", s);
+        }
+        Ok(Host::Dns(name))
+    }
+}
+
+/// Checks length and version of a v3 onion address (without the `.onion` suffix)
+fn parse_onion_v3(onion: &str) -> Result<(), anyhow::Error> {
+    if onion.len() != 56 {
+        anyhow::bail!(
+            "{}.onion is not a v3 onion address (expected 56 characters, got {})",
+            onion,
+            onion.len()
+        );
+    }
+    let decoded = base32::decode(base32::Alphabet::RFC4648 { padding: false }, onion)
+        .ok_or_else(|| anyhow::anyhow!("{}.onion is not valid base32", onion))?;
+    if decoded.len() != 35 || decoded[34] != 3 {
+        anyhow::bail!("{}.onion is not a v3 onion address", onion);
+    }
+    Ok(())
+}
+
+/// Parses `host[:port]`, where an IPv6 host must be bracketed
+pub fn parse_address(address: &str, default_port: u16) -> Result<(Host, u16), anyhow::Error> {
+    let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((ip, port)) => (&address[..ip.len() + 2], port),
+            None => anyhow::bail!("IPv6 address {} is missing a closing bracket", address),
+        }
+    } else if address.matches(':').count() > 1 {
+        anyhow::bail!("IPv6 address {} must be enclosed in brackets", address);
+    } else {
+        match address.find(':') {
+            Some(i) => (&address[..i], &address[i..]),
+            None => (address, ""),
+        }
+    };
+    let port = match port {
+        "" => default_port,
+        port => port
+            .strip_prefix(':')
+            .and_then(|p| p.parse::<u16>().ok())
+            .filter(|p| *p != 0)
+            .ok_or_else(|| anyhow::anyhow!("{} in {} is not a valid port", port, address))?,
+    };
+    Ok((host.parse()?, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_addrs() {
+        let addrs = iface_addrs("lo").unwrap();
+        assert!(addrs.contains(&IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(iface_addrs("no-such-interface").unwrap().is_empty());
+    }
+}
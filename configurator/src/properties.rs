@@ -0,0 +1,160 @@
+use std::fs::File;
+use std::path::Path;
+
+use crate::lndconnect::{self, LndConnect};
+use crate::watchtower::TowerInfo;
+use crate::{lncli, Config, Data, Properties, Property};
+
+const STATS_PATH: &str = "start9/stats.yaml";
+const ADMIN_MACAROON_PATH: &str = "data/chain/bitcoin/mainnet/admin.macaroon";
+const TLS_CERT_PATH: &str = "tls.cert";
+const CIPHER_SEED_PATH: &str = "start9/cipherSeedMnemonic.txt";
+
+const NO_CIPHER_SEED: &str = "The Aezeed Cipher Seed is only available on StartOS for LND wallets created with >= 16.4. It is not possible to retreive the Seed from wallets created on < 16.4.\nIf you are using a LND wallet created pre 16.4 but would like to have a Cipher Seed backup, you will need to close your existing channels and move any on-chain funds to an intermediate wallet before creating a new LND wallet with >= 16.4.";
+
+impl Property<String> {
+    fn string(value: String, description: &str, copyable: bool, qr: bool, masked: bool) -> Self {
+        Property {
+            value_type: "string".to_owned(),
+            value,
+            description: Some(description.to_owned()),
+            copyable,
+            qr,
+            masked,
+        }
+    }
+}
+
+/// Builds the Properties document from live getinfo and the artifacts on disk
+pub fn build(base_path: &Path) -> Result<Properties, anyhow::Error> {
+    let config: Config =
+        serde_yaml::from_reader(File::open(base_path.join("start9/config.yaml"))?)?;
+    let macaroon_path = base_path.join(ADMIN_MACAROON_PATH);
+    if !macaroon_path.exists() {
+        return Ok(Properties {
+            version: 2,
+            data: Data::NotReady {
+                not_ready: Property::string(
+                    "Could not find properties. The service might still be starting".to_owned(),
+                    "Fallback message for when properties cannot be found",
+                    false,
+                    false,
+                    false,
+                ),
+            },
+        });
+    }
+    let info = lncli::get_info()?;
+    let macaroon = std::fs::read(macaroon_path)?;
+    let cert = lndconnect::cert_der(&std::fs::read(base_path.join(TLS_CERT_PATH))?)?;
+    let cipher_seed = std::fs::read_to_string(base_path.join(CIPHER_SEED_PATH))
+        .map(|s| s.trim().to_owned())
+        .unwrap_or_else(|_| NO_CIPHER_SEED.to_owned());
+    let tower_uris = match TowerInfo::load(base_path) {
+        Ok(info) => info.map(|info| info.uris).unwrap_or_default(),
+        Err(e) => {
+            println!("Error reading tower info: {:?}", e);
+            Vec::new()
+        }
+    };
+    Ok(Properties {
+        version: 2,
+        data: Data::LND {
+            node_alias: Property::string(
+                info.alias,
+                "The friendly identifier for your node",
+                true,
+                false,
+                false,
+            ),
+            node_id: Property::string(
+                info.identity_pubkey.clone(),
+                "The node identifier that other nodes can use to connect to this node",
+                true,
+                false,
+                true,
+            ),
+            node_uri: Property::string(
+                format!("{}@{}:9735", info.identity_pubkey, config.peer_tor_address),
+                "Give this to others to allow them to add your LND node as a peer",
+                true,
+                true,
+                true,
+            ),
+            sync_height: Property::string(
+                info.block_height.to_string(),
+                "The latest block height LND has processed",
+                false,
+                false,
+                false,
+            ),
+            synced_to_chain: Property::string(
+                info.synced_to_chain.to_string(),
+                "Whether LND has caught up with the chain backend",
+                false,
+                false,
+                false,
+            ),
+            synced_to_graph: Property::string(
+                info.synced_to_graph.to_string(),
+                "Whether LND has caught up with the channel graph",
+                false,
+                false,
+                false,
+            ),
+            lnd_connect_grpc: Property::string(
+                LndConnect::new(&config.control_tor_address, 10009, macaroon.clone())
+                    .with_cert(cert)
+                    .to_string(),
+                "Use this for other applications that require a gRPC connection",
+                true,
+                true,
+                true,
+            ),
+            lnd_connect_rest: Property::string(
+                LndConnect::new(&config.control_tor_address, 8080, macaroon).to_string(),
+                "Use this for other applications that require a REST connection",
+                true,
+                true,
+                true,
+            ),
+            cipher_seed: Property::string(
+                cipher_seed,
+                "Seed for restoring on-chain ONLY funds. This seed has no knowledge of channel state. This is NOT a BIP-39 seed; As such it cannot be used to recover on-chain funds to any wallet other than LND.",
+                true,
+                false,
+                true,
+            ),
+            tower_server: tower_uris.first().map(|url| {
+                Property::string(
+                    url.clone(),
+                    "Sharing this URL with other LND nodes will allow them to use your server as a watchtower.",
+                    true,
+                    true,
+                    true,
+                )
+            }),
+            tower_server_uris: Some(tower_uris)
+                .filter(|uris| uris.len() > 1)
+                .map(|uris| {
+                    Property::string(
+                        uris.join(", "),
+                        "Every address your watchtower server can be reached at, including clearnet ones.",
+                        true,
+                        false,
+                        false,
+                    )
+                }),
+        },
+    })
+}
+
+/// Writes the Properties document to start9/stats.yaml, where compat.properties serves it from
+pub fn write(base_path: &Path) -> Result<(), anyhow::Error> {
+    let properties = build(base_path)?;
+    let path = base_path.join(STATS_PATH);
+    let tmp = path.with_extension("yaml.tmp");
+    std::fs::write(&tmp, serde_yaml::to_string(&properties)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,415 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::lncli;
+
+/// Written by the recover-from-seed action. Holds the seed, so it is removed as soon as the wallet exists
+const REQUEST_PATH: &str = "start9/recovery.yaml";
+const STATUS_PATH: &str = "start9/recovery-status.yaml";
+const SCB_PATH: &str = "start9/recovery.backup";
+/// Address look-ahead used when the config does not set one: a dead disk usually means a long wallet history
+pub const DEFAULT_RECOVERY_WINDOW: usize = 10000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RecoveryRequest {
+    pub cipher_seed_mnemonic: Vec<String>,
+    aezeed_passphrase: Option<String>,
+    /// base64 encoded channel.backup
+    multi_chan_backup: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Stage {
+    WalletRecovery,
+    ClosingChannels,
+    Complete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ChannelState {
+    /// waiting for the peer to force close (data loss protection)
+    WaitingClose,
+    PendingForceClose,
+    Closed,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ChannelRecovery {
+    /// unknown until LND lists the restored channel as pending
+    remote_pubkey: Option<String>,
+    state: ChannelState,
+    closing_txid: Option<String>,
+    limbo_balance: u64,
+    maturity_height: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RecoveryStatus {
+    stage: Stage,
+    recovery_window: usize,
+    wallet_recovery_progress: f64,
+    /// whether `channels` has been seeded with every channel in the restored backup
+    #[serde(default)]
+    scb_channels_known: bool,
+    channels: BTreeMap<String, ChannelRecovery>,
+}
+impl RecoveryStatus {
+    fn load(base_path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        let path = base_path.join(STATUS_PATH);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_yaml::from_reader(std::fs::File::open(path)?)?))
+    }
+
+    fn store(&self, base_path: &Path) -> Result<(), anyhow::Error> {
+        let path = base_path.join(STATUS_PATH);
+        let tmp = path.with_extension("yaml.tmp");
+        std::fs::write(&tmp, serde_yaml::to_string(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+pub fn pending_request(base_path: &Path) -> Result<Option<RecoveryRequest>, anyhow::Error> {
+    let path = base_path.join(REQUEST_PATH);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_yaml::from_reader(std::fs::File::open(path)?)?))
+}
+
+/// The recovery window to unlock with, so an interrupted wallet rescan picks up again after a restart
+pub fn resume_window(base_path: &Path) -> Result<Option<usize>, anyhow::Error> {
+    Ok(match RecoveryStatus::load(base_path)? {
+        Some(status) if status.stage == Stage::WalletRecovery => Some(status.recovery_window),
+        _ => None,
+    })
+}
+
+/// Creates the wallet from the requested seed and starts the rescan. Returns false
+/// if the wallet already existed, i.e. a previous start crashed right after creating it.
+/// The request is removed last, once everything else is persisted.
+pub fn init_wallet(
+    base_path: &Path,
+    request: &RecoveryRequest,
+    password_bytes: &[u8],
+    recovery_window: usize,
+) -> Result<bool, anyhow::Error> {
+    let scb = base64::decode(request.multi_chan_backup.trim())?;
+    let created = loop {
+        let output = Command::new("curl")
+            .arg("--no-progress-meter")
+            .arg("-X")
+            .arg("POST")
+            .arg("--cacert")
+            .arg("/root/.lnd/tls.cert")
+            .arg("https://lnd.embassy:8080/v1/initwallet")
+            .arg("-d")
+            .arg(serde_json::to_string(&crate::SkipNulls(
+                serde_json::json!({
+                    "wallet_password": base64::encode(password_bytes),
+                    "cipher_seed_mnemonic": request.cipher_seed_mnemonic,
+                    "aezeed_passphrase": request.aezeed_passphrase.as_ref().map(base64::encode),
+                    "recovery_window": recovery_window,
+                }),
+            ))?)
+            .output()?;
+        if !output.status.success() || output.stdout.is_empty() {
+            println!("Waiting for RPC to start...");
+            std::thread::sleep(Duration::from_secs(5));
+            continue;
+        }
+        let output: Value = serde_json::from_slice(&output.stdout)?;
+        match output.get("message").and_then(|m| m.as_str()) {
+            Some(s) if s.contains("waiting to start") => {
+                std::thread::sleep(Duration::from_secs(5));
+            }
+            Some(s) if s.contains("wallet already exists") => break false,
+            Some(s) => return Err(anyhow::anyhow!("Error recovering wallet: {}", s)),
+            None => break true,
+        }
+    };
+    std::fs::write(base_path.join(SCB_PATH), scb)?;
+    if created || RecoveryStatus::load(base_path)?.is_none() {
+        RecoveryStatus {
+            stage: Stage::WalletRecovery,
+            recovery_window,
+            wallet_recovery_progress: 0.0,
+            scb_channels_known: false,
+            channels: BTreeMap::new(),
+        }
+        .store(base_path)?;
+    }
+    std::fs::remove_file(base_path.join(REQUEST_PATH))?;
+    Ok(created)
+}
+
+#[derive(Deserialize)]
+struct RecoveryInfo {
+    recovery_finished: bool,
+    progress: f64,
+}
+
+#[derive(Deserialize)]
+struct PendingChannel {
+    remote_node_pub: String,
+    channel_point: String,
+}
+
+#[derive(Deserialize)]
+struct WaitingCloseChannel {
+    channel: PendingChannel,
+    #[serde(deserialize_with = "lncli::from_str")]
+    limbo_balance: u64,
+    closing_txid: String,
+}
+
+#[derive(Deserialize)]
+struct ForceClosedChannel {
+    channel: PendingChannel,
+    #[serde(deserialize_with = "lncli::from_str")]
+    limbo_balance: u64,
+    closing_txid: String,
+    maturity_height: u32,
+}
+
+#[derive(Deserialize)]
+struct PendingChannels {
+    waiting_close_channels: Vec<WaitingCloseChannel>,
+    pending_force_closing_channels: Vec<ForceClosedChannel>,
+}
+
+#[derive(Deserialize)]
+struct ClosedChannel {
+    channel_point: String,
+    closing_tx_hash: String,
+}
+
+#[derive(Deserialize)]
+struct ClosedChannels {
+    channels: Vec<ClosedChannel>,
+}
+
+/// `lncli exportchanbackup --all`; restored channels are exported like open ones
+#[derive(Deserialize)]
+struct ChanBackupSnapshot {
+    multi_chan_backup: MultiChanBackup,
+}
+
+#[derive(Deserialize)]
+struct MultiChanBackup {
+    #[serde(default)]
+    chan_points: Vec<ChannelPoint>,
+}
+
+#[derive(Deserialize)]
+struct ChannelPoint {
+    /// base64 of the txid in internal (reversed) byte order
+    #[serde(default)]
+    funding_txid_bytes: Option<String>,
+    #[serde(default)]
+    funding_txid_str: Option<String>,
+    #[serde(default)]
+    output_index: u32,
+}
+impl ChannelPoint {
+    fn to_outpoint(&self) -> Result<String, anyhow::Error> {
+        let txid = match (&self.funding_txid_str, &self.funding_txid_bytes) {
+            (Some(txid), _) => txid.clone(),
+            (None, Some(bytes)) => {
+                let mut bytes = base64::decode(bytes)?;
+                bytes.reverse();
+                hex::encode(bytes)
+            }
+            (None, None) => anyhow::bail!("channel point without a funding txid"),
+        };
+        Ok(format!("{}:{}", txid, self.output_index))
+    }
+}
+
+/// Every channel the restored backup knows about, so recovery cannot complete
+/// before each of them has been seen closing
+fn seed_channels(status: &mut RecoveryStatus) -> Result<(), anyhow::Error> {
+    let snapshot: ChanBackupSnapshot = lncli::call(&["exportchanbackup", "--all"])?;
+    for point in snapshot.multi_chan_backup.chan_points {
+        status
+            .channels
+            .entry(point.to_outpoint()?)
+            .or_insert(ChannelRecovery {
+                remote_pubkey: None,
+                state: ChannelState::WaitingClose,
+                closing_txid: None,
+                limbo_balance: 0,
+                maturity_height: None,
+            });
+    }
+    status.scb_channels_known = true;
+    Ok(())
+}
+
+/// Recovery is complete once the backup's channels are known and all of them
+/// are closed. An empty map only counts when the backup really had no channels.
+fn all_closed(status: &RecoveryStatus) -> bool {
+    status.scb_channels_known
+        && status
+            .channels
+            .values()
+            .all(|c| c.state == ChannelState::Closed)
+}
+
+fn non_empty(s: String) -> Option<String> {
+    Some(s).filter(|s| !s.is_empty())
+}
+
+/// Advances an ongoing disaster recovery by one step, recording progress in the status file
+pub fn step(base_path: &Path) -> Result<(), anyhow::Error> {
+    let mut status = match RecoveryStatus::load(base_path)? {
+        Some(status) => status,
+        None => return Ok(()),
+    };
+    match status.stage {
+        Stage::WalletRecovery => {
+            let info: RecoveryInfo = lncli::call(&["getrecoveryinfo"])?;
+            status.wallet_recovery_progress = info.progress;
+            if info.recovery_finished {
+                println!("Wallet recovery finished. Restoring channels from the channel backup...");
+                let scb_path = base_path.join(SCB_PATH);
+                lncli::call::<Value>(&[
+                    "restorechanbackup",
+                    "--multi_file",
+                    &scb_path.display().to_string(),
+                ])?;
+                println!("SCB recovery initiated.");
+                status.stage = Stage::ClosingChannels;
+            }
+        }
+        Stage::ClosingChannels => {
+            if !status.scb_channels_known {
+                seed_channels(&mut status)?;
+            }
+            let pending: PendingChannels = lncli::call(&["pendingchannels"])?;
+            let mut still_pending = Vec::new();
+            for c in pending.waiting_close_channels {
+                still_pending.push(c.channel.channel_point.clone());
+                status.channels.insert(
+                    c.channel.channel_point,
+                    ChannelRecovery {
+                        remote_pubkey: Some(c.channel.remote_node_pub),
+                        state: ChannelState::WaitingClose,
+                        closing_txid: non_empty(c.closing_txid),
+                        limbo_balance: c.limbo_balance,
+                        maturity_height: None,
+                    },
+                );
+            }
+            for c in pending.pending_force_closing_channels {
+                still_pending.push(c.channel.channel_point.clone());
+                status.channels.insert(
+                    c.channel.channel_point,
+                    ChannelRecovery {
+                        remote_pubkey: Some(c.channel.remote_node_pub),
+                        state: ChannelState::PendingForceClose,
+                        closing_txid: non_empty(c.closing_txid),
+                        limbo_balance: c.limbo_balance,
+                        maturity_height: Some(c.maturity_height).filter(|h| *h > 0),
+                    },
+                );
+            }
+            let resolved: Vec<&String> = status
+                .channels
+                .iter()
+                .filter(|(point, c)| {
+                    c.state != ChannelState::Closed && !still_pending.contains(point)
+                })
+                .map(|(point, _)| point)
+                .collect();
+            if !resolved.is_empty() {
+                let closed: ClosedChannels = lncli::call(&["closedchannels"])?;
+                for c in closed.channels {
+                    if let Some(channel) = status.channels.get_mut(&c.channel_point) {
+                        channel.state = ChannelState::Closed;
+                        channel.closing_txid = non_empty(c.closing_tx_hash);
+                        channel.limbo_balance = 0;
+                    }
+                }
+            }
+            if all_closed(&status) {
+                println!("Disaster recovery complete. All channels are closed.");
+                status.stage = Stage::Complete;
+            }
+        }
+        Stage::Complete => return Ok(()),
+    }
+    status.store(base_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(scb_channels_known: bool, states: &[ChannelState]) -> RecoveryStatus {
+        RecoveryStatus {
+            stage: Stage::ClosingChannels,
+            recovery_window: 0,
+            wallet_recovery_progress: 1.0,
+            scb_channels_known,
+            channels: states
+                .iter()
+                .enumerate()
+                .map(|(i, state)| {
+                    (
+                        format!("txid:{}", i),
+                        ChannelRecovery {
+                            remote_pubkey: None,
+                            state: *state,
+                            closing_txid: None,
+                            limbo_balance: 0,
+                            maturity_height: None,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn completion_needs_known_channels() {
+        assert!(!all_closed(&status(false, &[])));
+        assert!(!all_closed(&status(false, &[ChannelState::Closed])));
+        assert!(all_closed(&status(true, &[])));
+        assert!(!all_closed(&status(
+            true,
+            &[ChannelState::Closed, ChannelState::WaitingClose]
+        )));
+        assert!(all_closed(&status(
+            true,
+            &[ChannelState::Closed, ChannelState::Closed]
+        )));
+    }
+
+    #[test]
+    fn channel_point_from_bytes() {
+        let mut internal = [0u8; 32];
+        internal[0] = 0xab;
+        let point = ChannelPoint {
+            funding_txid_bytes: Some(base64::encode(internal)),
+            funding_txid_str: None,
+            output_index: 1,
+        };
+        assert_eq!(
+            point.to_outpoint().unwrap(),
+            format!("{}ab:1", "00".repeat(31))
+        );
+    }
+}
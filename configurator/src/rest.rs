@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::Certificate;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+const REST_URL: &str = "https://lnd.embassy:8080";
+const TLS_CERT_PATH: &str = "/root/.lnd/tls.cert";
+const ADMIN_MACAROON_PATH: &str = "/root/.lnd/data/chain/bitcoin/mainnet/admin.macaroon";
+
+#[derive(Deserialize)]
+struct ErrorRes {
+    message: String,
+}
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// Every certificate in a PEM file. On StartOS tls.cert holds the whole chain, and rustls
+/// does not accept the leaf it starts with as a trust anchor.
+fn root_certificates(pem: &[u8]) -> Result<Vec<Certificate>, anyhow::Error> {
+    let certs = std::str::from_utf8(pem)?
+        .split_inclusive(PEM_END)
+        .filter_map(|block| block.find(PEM_BEGIN).map(|start| &block[start..]))
+        .map(|block| Ok(Certificate::from_pem(block.as_bytes())?))
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("no certificate in {}", TLS_CERT_PATH));
+    }
+    Ok(certs)
+}
+
+fn client() -> Result<Client, anyhow::Error> {
+    let mut builder = Client::builder()
+        .tls_built_in_root_certs(false)
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(30));
+    for cert in root_certificates(&std::fs::read(TLS_CERT_PATH)?)? {
+        builder = builder.add_root_certificate(cert);
+    }
+    Ok(builder.build()?)
+}
+
+fn send<T: DeserializeOwned>(req: RequestBuilder, mac: &[u8]) -> Result<T, anyhow::Error> {
+    let res = req
+        .header("Grpc-Metadata-macaroon", hex::encode_upper(mac))
+        .send()?;
+    let status = res.status();
+    let body = res.bytes()?;
+    if !status.is_success() {
+        let message = serde_json::from_slice::<ErrorRes>(&body)
+            .map(|e| e.message)
+            .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+        return Err(anyhow::anyhow!("LND returned {}: {}", status, message));
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
+
+pub fn admin_macaroon() -> Result<Vec<u8>, anyhow::Error> {
+    Ok(std::fs::read(ADMIN_MACAROON_PATH)?)
+}
+
+/// Calls an LND REST endpoint with the admin macaroon
+pub fn get<T: DeserializeOwned>(endpoint: &str) -> Result<T, anyhow::Error> {
+    send(
+        client()?.get(format!("{}{}", REST_URL, endpoint)),
+        &admin_macaroon()?,
+    )
+}
+
+/// Calls an LND REST endpoint with the given macaroon
+pub fn post_as<T: DeserializeOwned>(
+    mac: &[u8],
+    endpoint: &str,
+    body: &serde_json::Value,
+) -> Result<T, anyhow::Error> {
+    send(
+        client()?
+            .post(format!("{}{}", REST_URL, endpoint))
+            .json(body),
+        mac,
+    )
+}
+
+pub fn delete<T: DeserializeOwned>(endpoint: &str) -> Result<T, anyhow::Error> {
+    send(
+        client()?.delete(format!("{}{}", REST_URL, endpoint)),
+        &admin_macaroon()?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_certificate_chain() {
+        // health-check/src/lnd.rs tests the handshake against a real chain
+        let chain = format!(
+            "{b}\nbGVhZg==\n{e}\n{b}\naW50ZXJtZWRpYXRl\n{e}\n\n{b}\ncm9vdA==\n{e}\n",
+            b = PEM_BEGIN,
+            e = PEM_END
+        );
+        assert_eq!(root_certificates(chain.as_bytes()).unwrap().len(), 3);
+        assert!(root_certificates(b"").is_err());
+    }
+}
@@ -0,0 +1,619 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bitcoincore_rpc::bitcoin::secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::lncli;
+use crate::net::{parse_address, Host};
+
+pub const DEFAULT_PORT: u16 = 9911;
+const STATUS_DIR: &str = "start9/watchtowers";
+pub const TOWER_INFO_PATH: &str = "start9/tower-info.json";
+/// written by older versions, one URI per line
+const LEGACY_TOWER_URL_PATH: &str = "start9/towerServerUrl";
+const INITIAL_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+/// `<pubkey>@<host>[:<port>]`, as accepted by `lncli wtclient add`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchtowerUri {
+    pub pubkey: PublicKey,
+    pub host: Host,
+    pub port: u16,
+}
+impl WatchtowerUri {
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+impl fmt::Display for WatchtowerUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.pubkey, self.address())
+    }
+}
+impl FromStr for WatchtowerUri {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (pubkey, address) = match s.split_once('@') {
+            Some(split) => split,
+            None => anyhow::bail!("watchtower URI {} must have the form pubkey@host:port", s),
+        };
+        if pubkey.len() != 66 || !pubkey.starts_with("02") && !pubkey.starts_with("03") {
+            anyhow::bail!(
+                "watchtower pubkey {} must be a 33-byte compressed public key in hex (66 characters starting with 02 or 03)",
+                pubkey
+            );
+        }
+        let bytes = hex::decode(pubkey)
+            .map_err(|_| anyhow::anyhow!("watchtower pubkey {} is not valid hex", pubkey))?;
+        let pubkey = PublicKey::from_slice(&bytes).map_err(|_| {
+            anyhow::anyhow!("watchtower pubkey {} is not a valid public key", pubkey)
+        })?;
+        if address.is_empty() {
+            anyhow::bail!("watchtower URI {} is missing a host", s);
+        }
+        let (host, port) = parse_address(address, DEFAULT_PORT)?;
+        Ok(WatchtowerUri { pubkey, host, port })
+    }
+}
+
+/// A tower as reported by `lncli wtclient towers`
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tower {
+    pub pubkey: String,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub active_session_candidate: bool,
+}
+
+#[derive(Deserialize)]
+struct Towers {
+    #[serde(default)]
+    towers: Vec<Tower>,
+}
+
+pub fn towers() -> Result<Vec<Tower>, anyhow::Error> {
+    Ok(lncli::call::<Towers>(&["wtclient", "towers"])?.towers)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum Change {
+    /// adds a tower, a new address for it, or reactivates it
+    Add {
+        uri: String,
+    },
+    /// stops using the tower for new sessions
+    Remove {
+        pubkey: String,
+    },
+    RemoveAddress {
+        pubkey: String,
+        address: String,
+    },
+}
+impl Change {
+    fn pubkey(&self) -> &str {
+        match self {
+            Change::Add { uri } => uri.split('@').next().unwrap_or_default(),
+            Change::Remove { pubkey } | Change::RemoveAddress { pubkey, .. } => pubkey,
+        }
+    }
+
+    pub fn apply(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Change::Add { uri } => lncli::run(&["wtclient", "add", uri]),
+            Change::Remove { pubkey } => lncli::run(&["wtclient", "remove", pubkey]),
+            Change::RemoveAddress { pubkey, address } => {
+                lncli::run(&["wtclient", "remove", pubkey, address])
+            }
+        }?;
+        Ok(())
+    }
+}
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Add { uri } => write!(f, "add {}", uri),
+            Change::Remove { pubkey } => write!(f, "remove {}", pubkey),
+            Change::RemoveAddress { pubkey, address } => {
+                write!(f, "remove address {} from {}", address, pubkey)
+            }
+        }
+    }
+}
+
+/// The changes that make the client's tower list match `configured`.
+/// Additions come first so a tower never loses its last address.
+///
+/// LND stores the resolved IP of a tower configured by host name, so such towers
+/// are matched by pubkey only, and addresses that cannot be mapped back to the
+/// configuration are never removed from them.
+pub fn plan(configured: &[WatchtowerUri], towers: &[Tower]) -> Vec<Change> {
+    let mut wanted: BTreeMap<String, Vec<&WatchtowerUri>> = BTreeMap::new();
+    for uri in configured {
+        wanted.entry(uri.pubkey.to_string()).or_default().push(uri);
+    }
+    let existing: BTreeMap<&str, &Tower> = towers.iter().map(|t| (t.pubkey.as_str(), t)).collect();
+    let mut adds = Vec::new();
+    let mut removals = Vec::new();
+    for (pubkey, uris) in &wanted {
+        let tower = existing
+            .get(pubkey.as_str())
+            .filter(|t| t.active_session_candidate);
+        for uri in uris {
+            let known = tower.is_some_and(|t| match uri.host {
+                Host::Dns(_) => true,
+                _ => t.addresses.contains(&uri.address()),
+            });
+            if !known {
+                adds.push(Change::Add {
+                    uri: format!("{}@{}", pubkey, uri.address()),
+                });
+            }
+        }
+        if uris.iter().any(|uri| matches!(uri.host, Host::Dns(_))) {
+            continue;
+        }
+        let addresses: BTreeSet<String> = uris.iter().map(|uri| uri.address()).collect();
+        if let Some(tower) = tower {
+            for address in &tower.addresses {
+                if !addresses.contains(address) {
+                    removals.push(Change::RemoveAddress {
+                        pubkey: pubkey.clone(),
+                        address: address.clone(),
+                    });
+                }
+            }
+        }
+    }
+    for tower in towers {
+        if tower.active_session_candidate && !wanted.contains_key(&tower.pubkey) {
+            removals.push(Change::Remove {
+                pubkey: tower.pubkey.clone(),
+            });
+        }
+    }
+    adds.extend(removals);
+    adds
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RegistrationState {
+    Pending,
+    Retrying,
+    Done,
+}
+
+/// Written to `start9/watchtowers/<pubkey>.yaml` while a tower is being reconciled
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TowerStatus {
+    pub pubkey: String,
+    pub state: RegistrationState,
+    /// what is still left to do, in order
+    pub changes: Vec<String>,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// unix seconds
+    pub updated_at: u64,
+    /// unix seconds
+    pub next_attempt_at: Option<u64>,
+}
+impl TowerStatus {
+    fn new(pubkey: &str, state: RegistrationState, changes: &[Change]) -> Self {
+        TowerStatus {
+            pubkey: pubkey.to_owned(),
+            state,
+            changes: changes.iter().map(|c| c.to_string()).collect(),
+            attempts: 0,
+            last_error: None,
+            updated_at: unix_now(),
+            next_attempt_at: None,
+        }
+    }
+
+    fn store(&self, base_path: &Path) {
+        let path = base_path
+            .join(STATUS_DIR)
+            .join(format!("{}.yaml", self.pubkey));
+        let tmp = path.with_extension("yaml.tmp");
+        let res = serde_yaml::to_string(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| Ok(std::fs::write(&tmp, s)?))
+            .and_then(|_| Ok(std::fs::rename(&tmp, &path)?));
+        if let Err(e) = res {
+            println!(
+                "Error writing watchtower status for {}: {:?}",
+                self.pubkey, e
+            );
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Doubles from INITIAL_BACKOFF up to MAX_BACKOFF
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .checked_mul(1 << attempt.min(16))
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+}
+
+/// Applies one tower's changes in order, retrying the failed one with backoff
+fn register(base_path: &Path, pubkey: &str, mut changes: Vec<Change>) {
+    let mut status = TowerStatus::new(pubkey, RegistrationState::Pending, &changes);
+    status.store(base_path);
+    while !changes.is_empty() {
+        status.attempts += 1;
+        match changes[0].apply() {
+            Ok(()) => {
+                println!("Watchtower client: {}", changes[0]);
+                changes.remove(0);
+                status.attempts = 0;
+                status.last_error = None;
+                status.next_attempt_at = None;
+            }
+            Err(e) => {
+                let delay = backoff(status.attempts - 1);
+                println!(
+                    "Watchtower client: {} failed, retrying in {}s: {}",
+                    changes[0],
+                    delay.as_secs(),
+                    e
+                );
+                status.state = RegistrationState::Retrying;
+                status.last_error = Some(e.to_string());
+                status.next_attempt_at = Some(unix_now() + delay.as_secs());
+                status.updated_at = unix_now();
+                status.store(base_path);
+                std::thread::sleep(delay);
+                continue;
+            }
+        }
+        status.changes = changes.iter().map(|c| c.to_string()).collect();
+        status.updated_at = unix_now();
+        status.store(base_path);
+    }
+    status.state = RegistrationState::Done;
+    status.updated_at = unix_now();
+    status.store(base_path);
+}
+
+/// Reconciles the client's towers in the background, one thread per tower, so an
+/// unreachable tower delays neither the others nor the rest of the configurator
+pub fn spawn_registration(base_path: PathBuf, configured: Vec<WatchtowerUri>) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut attempt = 0;
+        let towers = loop {
+            match towers() {
+                Ok(towers) => break towers,
+                Err(e) => {
+                    let delay = backoff(attempt);
+                    println!(
+                        "Watchtower client not ready, retrying in {}s: {}",
+                        delay.as_secs(),
+                        e
+                    );
+                    attempt += 1;
+                    std::thread::sleep(delay);
+                }
+            }
+        };
+        if let Err(e) = std::fs::create_dir_all(base_path.join(STATUS_DIR)) {
+            println!("Error creating watchtower status directory: {:?}", e);
+        }
+        let mut by_tower: BTreeMap<String, Vec<Change>> = configured
+            .iter()
+            .map(|uri| (uri.pubkey.to_string(), Vec::new()))
+            .collect();
+        for change in plan(&configured, &towers) {
+            by_tower
+                .entry(change.pubkey().to_owned())
+                .or_default()
+                .push(change);
+        }
+        // drop statuses of towers that are neither configured nor being removed
+        if let Ok(entries) = std::fs::read_dir(base_path.join(STATUS_DIR)) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let known = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|pubkey| by_tower.contains_key(pubkey));
+                if !known {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+        let handles: Vec<_> = by_tower
+            .into_iter()
+            .map(|(pubkey, changes)| {
+                let base_path = base_path.clone();
+                std::thread::spawn(move || register(&base_path, &pubkey, changes))
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+        println!("Watchtower client matches the configured towers");
+    })
+}
+
+/// Our own tower, as reported by `lncli tower info`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TowerInfo {
+    pub pubkey: String,
+    #[serde(default)]
+    pub listeners: Vec<String>,
+    #[serde(default)]
+    pub uris: Vec<String>,
+}
+impl TowerInfo {
+    pub fn fetch() -> Result<Self, anyhow::Error> {
+        lncli::call(&["tower", "info"])
+    }
+
+    pub fn load(base_path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        match std::fs::read(base_path.join(TOWER_INFO_PATH)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&self, base_path: &Path) -> Result<(), anyhow::Error> {
+        let path = base_path.join(TOWER_INFO_PATH);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Removes the tower info record when the server is disabled
+pub fn clear_tower_info(base_path: &Path) {
+    for path in [TOWER_INFO_PATH, LEGACY_TOWER_URL_PATH] {
+        match std::fs::remove_file(base_path.join(path)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                println!("Error removing {}: {}", path, e)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Records our tower's info in the background once LND reports at least one URI
+pub fn spawn_tower_info(base_path: PathBuf) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let _ = std::fs::remove_file(base_path.join(LEGACY_TOWER_URL_PATH));
+        let mut attempt = 0;
+        loop {
+            let res = TowerInfo::fetch().and_then(|info| {
+                if info.uris.is_empty() {
+                    anyhow::bail!("the tower has no URIs yet");
+                }
+                info.store(&base_path)?;
+                Ok(info)
+            });
+            match res {
+                Ok(info) => {
+                    println!("Tower server URIs: {}", info.uris.join(", "));
+                    break;
+                }
+                Err(e) => {
+                    let delay = backoff(attempt);
+                    println!(
+                        "Tower server info not available, retrying in {}s: {:?}",
+                        delay.as_secs(),
+                        e
+                    );
+                    attempt += 1;
+                    std::thread::sleep(delay);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use super::*;
+
+    const ONION: &str = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd";
+
+    fn valid_pubkey() -> String {
+        // the generator point; always on the curve
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".to_owned()
+    }
+
+    fn tower(pubkey: &str, addresses: &[&str], active: bool) -> Tower {
+        Tower {
+            pubkey: pubkey.to_owned(),
+            addresses: addresses.iter().map(|a| (*a).to_owned()).collect(),
+            active_session_candidate: active,
+        }
+    }
+
+    #[test]
+    fn plan_reconciles() {
+        let other = "03".to_owned() + &valid_pubkey()[2..];
+        let configured: Vec<WatchtowerUri> = vec![
+            format!("{}@1.2.3.4", valid_pubkey()).parse().unwrap(),
+            format!("{}@{}.onion", valid_pubkey(), ONION)
+                .parse()
+                .unwrap(),
+        ];
+        let towers = vec![
+            tower(&valid_pubkey(), &["1.2.3.4:9911", "5.6.7.8:9911"], true),
+            tower(&other, &["9.9.9.9:9911"], true),
+        ];
+        assert_eq!(
+            plan(&configured, &towers),
+            vec![
+                Change::Add {
+                    uri: format!("{}@{}.onion:9911", valid_pubkey(), ONION)
+                },
+                Change::RemoveAddress {
+                    pubkey: valid_pubkey(),
+                    address: "5.6.7.8:9911".to_owned()
+                },
+                Change::Remove { pubkey: other },
+            ]
+        );
+    }
+
+    #[test]
+    fn plan_matches_dns_towers_by_pubkey() {
+        let configured: Vec<WatchtowerUri> = vec![format!("{}@tower.example.com", valid_pubkey())
+            .parse()
+            .unwrap()];
+        // LND only knows the address the name resolved to
+        let active = vec![tower(&valid_pubkey(), &["93.184.216.34:9911"], true)];
+        assert!(plan(&configured, &active).is_empty());
+        let inactive = vec![tower(&valid_pubkey(), &["93.184.216.34:9911"], false)];
+        assert_eq!(
+            plan(&configured, &inactive),
+            vec![Change::Add {
+                uri: format!("{}@tower.example.com:9911", valid_pubkey())
+            }]
+        );
+        assert_eq!(
+            plan(&configured, &[]),
+            vec![Change::Add {
+                uri: format!("{}@tower.example.com:9911", valid_pubkey())
+            }]
+        );
+        // alongside a literal address, only that one is checked
+        let mut configured = configured;
+        configured.push(format!("{}@1.2.3.4", valid_pubkey()).parse().unwrap());
+        assert_eq!(
+            plan(&configured, &active),
+            vec![Change::Add {
+                uri: format!("{}@1.2.3.4:9911", valid_pubkey())
+            }]
+        );
+    }
+
+    #[test]
+    fn plan_reactivates_and_settles() {
+        let configured: Vec<WatchtowerUri> =
+            vec![format!("{}@1.2.3.4", valid_pubkey()).parse().unwrap()];
+        let inactive = vec![tower(&valid_pubkey(), &["1.2.3.4:9911"], false)];
+        assert_eq!(
+            plan(&configured, &inactive),
+            vec![Change::Add {
+                uri: format!("{}@1.2.3.4:9911", valid_pubkey())
+            }]
+        );
+        let active = vec![tower(&valid_pubkey(), &["1.2.3.4:9911"], true)];
+        assert!(plan(&configured, &active).is_empty());
+        assert!(plan(&[], &inactive).is_empty());
+    }
+
+    #[test]
+    fn tower_info_without_uris() {
+        let info: TowerInfo = serde_json::from_str(&format!(
+            r#"{{"pubkey": "{}", "listeners": ["[::]:9911"]}}"#,
+            valid_pubkey()
+        ))
+        .unwrap();
+        assert!(info.uris.is_empty());
+        assert!(serde_json::from_str::<TowerInfo>(r#"{"uris": []}"#).is_err());
+    }
+
+    #[test]
+    fn backoff_is_bounded() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+        assert_eq!(backoff(1), INITIAL_BACKOFF * 2);
+        assert_eq!(backoff(20), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn default_port() {
+        let uri: WatchtowerUri = format!("{}@1.2.3.4", valid_pubkey()).parse().unwrap();
+        assert_eq!(uri.host, Host::Ipv4("1.2.3.4".parse().unwrap()));
+        assert_eq!(uri.port, DEFAULT_PORT);
+        assert_eq!(uri.to_string(), format!("{}@1.2.3.4:9911", valid_pubkey()));
+    }
+
+    #[test]
+    fn host_kinds() {
+        let uri: WatchtowerUri = format!("{}@[::1]:9912", valid_pubkey()).parse().unwrap();
+        assert_eq!(uri.host, Host::Ipv6(Ipv6Addr::LOCALHOST));
+        assert_eq!(uri.address(), "[::1]:9912");
+        let uri: WatchtowerUri = format!("{}@[::1]", valid_pubkey()).parse().unwrap();
+        assert_eq!(uri.port, DEFAULT_PORT);
+        let uri: WatchtowerUri = format!("{}@Tower.Example.com:9911", valid_pubkey())
+            .parse()
+            .unwrap();
+        assert_eq!(uri.host, Host::Dns("tower.example.com".to_owned()));
+        let uri: WatchtowerUri = format!("{}@{}.onion:9911", valid_pubkey(), ONION)
+            .parse()
+            .unwrap();
+        assert_eq!(uri.host, Host::Onion(format!("{}.onion", ONION)));
+    }
+
+    #[test]
+    fn rejects_bad_pubkeys() {
+        for pubkey in [
+            "",
+            "abc",
+            &valid_pubkey()[2..],
+            &format!("04{}", &valid_pubkey()[2..]),
+            &format!("02{}", "zz".repeat(32)),
+            // x = 5 has no point on the curve
+            &format!("02{:064x}", 5),
+        ] {
+            assert!(format!("{}@1.2.3.4", pubkey)
+                .parse::<WatchtowerUri>()
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_bad_addresses() {
+        for address in [
+            "",
+            "1.2.3.4:0",
+            "1.2.3.4:65536",
+            "1.2.3.4:port",
+            "::1",
+            "[::1",
+            "[::1]9911",
+            "[not-ip]:9911",
+            "256.1.1.1",
+            "-bad.example.com",
+            "bad_host.com",
+            "tooshort.onion",
+            &format!("{}a.onion", ONION),
+        ] {
+            assert!(
+                format!("{}@{}", valid_pubkey(), address)
+                    .parse::<WatchtowerUri>()
+                    .is_err(),
+                "{} should be rejected",
+                address
+            );
+        }
+        assert!(valid_pubkey().parse::<WatchtowerUri>().is_err());
+    }
+}
@@ -28,8 +28,6 @@ while ! openssl x509 -text -noout -in /mnt/cert/control.cert.pem -ext subjectAlt
 done
 
 # copy system cert
-openssl x509 -outform der -in /mnt/cert/control.cert.pem -out /root/.lnd/start9/control.cert.der
-cat /root/.lnd/start9/control.cert.der | basenc --base64url -w0 > /root/.lnd/start9/control.cert.pem.base64url
 cp /mnt/cert/control.cert.pem /root/.lnd/tls.cert
 cp /mnt/cert/control.key.pem /root/.lnd/tls.key
 cp /mnt/cert/control.cert.pem /root/.lnd/public/tls.cert
@@ -46,14 +44,6 @@ else
 fi
 lnd_child=$!
 
-while ! [ -e /root/.lnd/data/chain/bitcoin/mainnet/admin.macaroon ]; do
-  echo "Waiting for lnd to create macaroon..."
-  sleep 30
-done
-
-cat /root/.lnd/data/chain/bitcoin/mainnet/admin.macaroon | basenc --base16 -w0  > /root/.lnd/start9/admin.macaroon.hex
-cat /root/.lnd/data/chain/bitcoin/mainnet/admin.macaroon | basenc --base64url -w0  > /root/.lnd/start9/admin.macaroon.base64url
-
 trap _term SIGTERM
 
 wait $lnd_child $configurator_child $rest_child $grpc_child
@@ -0,0 +1,41 @@
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+
+use crate::lnd;
+
+#[derive(serde::Deserialize)]
+struct RpcRes<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(serde::Deserialize)]
+struct RpcError {
+    message: String,
+}
+
+/// Calls Bitcoin Core over RPC, using the credentials the configurator wrote to lnd.conf
+pub fn call<T: DeserializeOwned>(method: &str) -> Result<T, anyhow::Error> {
+    let host = lnd::conf_value("bitcoind.rpchost")?.unwrap_or_default();
+    let user = lnd::conf_value("bitcoind.rpcuser")?.unwrap_or_default();
+    let pass = lnd::conf_value("bitcoind.rpcpass")?;
+    let res: RpcRes<T> = Client::builder()
+        .connect_timeout(lnd::CONNECT_TIMEOUT)
+        .timeout(lnd::READ_TIMEOUT)
+        .build()?
+        .post(format!("http://{}/", host))
+        .basic_auth(user, pass)
+        .json(&serde_json::json!({
+            "jsonrpc": "1.0",
+            "id": "health-check",
+            "method": method,
+            "params": [],
+        }))
+        .send()?
+        .json()?;
+    match (res.result, res.error) {
+        (_, Some(e)) => Err(anyhow::anyhow!("{}", e.message)),
+        (Some(result), None) => Ok(result),
+        (None, None) => Err(anyhow::anyhow!("Empty response to {}", method)),
+    }
+}
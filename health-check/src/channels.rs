@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::lnd;
+
+/// The REST gateway encodes 64-bit integers as strings
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Channel {
+    #[serde(default)]
+    pub active: bool,
+    pub remote_pubkey: String,
+    pub channel_point: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListChannels {
+    #[serde(default)]
+    channels: Vec<Channel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PendingChannel {
+    pub remote_node_pub: String,
+    pub channel_point: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WaitingCloseChannel {
+    pub channel: PendingChannel,
+    #[serde(default, deserialize_with = "from_str")]
+    pub limbo_balance: i64,
+    #[serde(default)]
+    pub closing_txid: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForceClosedChannel {
+    pub channel: PendingChannel,
+    #[serde(default, deserialize_with = "from_str")]
+    pub limbo_balance: i64,
+    #[serde(default)]
+    pub closing_txid: String,
+    #[serde(default)]
+    pub maturity_height: u32,
+    #[serde(default)]
+    pub blocks_til_maturity: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PendingChannels {
+    #[serde(default)]
+    pub waiting_close_channels: Vec<WaitingCloseChannel>,
+    #[serde(default)]
+    pub pending_force_closing_channels: Vec<ForceClosedChannel>,
+}
+
+pub fn list() -> Result<Vec<Channel>, anyhow::Error> {
+    Ok(lnd::get::<ListChannels>("/v1/channels")?.channels)
+}
+
+pub fn pending() -> Result<PendingChannels, anyhow::Error> {
+    lnd::get("/v1/channels/pending")
+}
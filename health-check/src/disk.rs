@@ -0,0 +1,301 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+pub const LND_DIR: &str = "/root/.lnd";
+pub const CHANNEL_DB_PATH: &str = "/root/.lnd/data/graph/mainnet/channel.db";
+pub const WALLET_DB_PATH: &str = "/root/.lnd/data/chain/bitcoin/mainnet/wallet.db";
+/// Written by every run of the disk check, for the properties page and for support
+const STATUS_PATH: &str = "/root/.lnd/start9/disk-usage.yaml";
+
+const BOLT_MAGIC: u32 = 0xED0C_DAED;
+const PAGE_HEADER_SIZE: u64 = 16;
+/// Freelist page id when LND runs with db.bolt.nofreelistsync
+const NO_FREELIST: u64 = u64::MAX;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DbUsage {
+    pub size: u64,
+    /// unix seconds, if LND ever compacted this database
+    pub last_compacted: Option<u64>,
+    /// free pages plus the preallocated tail of the file, which compaction would hand back
+    pub reclaimable: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DiskUsage {
+    pub checked_at: u64,
+    pub free: u64,
+    pub total: u64,
+    pub channel_db: Option<DbUsage>,
+    pub wallet_db: Option<DbUsage>,
+}
+impl DiskUsage {
+    pub fn measure() -> Result<Self, anyhow::Error> {
+        let stat = nix::sys::statvfs::statvfs(LND_DIR)?;
+        let fragment = stat.fragment_size() as u64;
+        Ok(DiskUsage {
+            checked_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            free: stat.blocks_available() as u64 * fragment,
+            total: stat.blocks() as u64 * fragment,
+            channel_db: db_usage(Path::new(CHANNEL_DB_PATH))?,
+            wallet_db: db_usage(Path::new(WALLET_DB_PATH))?,
+        })
+    }
+
+    pub fn store(&self) -> Result<(), anyhow::Error> {
+        let path = Path::new(STATUS_PATH);
+        let tmp = path.with_extension("yaml.tmp");
+        std::fs::write(&tmp, serde_yaml::to_string(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+fn db_usage(path: &Path) -> Result<Option<DbUsage>, anyhow::Error> {
+    let size = match std::fs::metadata(path) {
+        Ok(m) => m.len(),
+        Err(_) => return Ok(None),
+    };
+    Ok(Some(DbUsage {
+        size,
+        last_compacted: last_compacted(path),
+        // the file is live, so a torn read only costs us the estimate
+        reclaimable: reclaimable(path, size).ok(),
+    }))
+}
+
+/// LND stores the time of the last compaction as big endian unix nanoseconds in `<db>.last-compacted`
+fn last_compacted(path: &Path) -> Option<u64> {
+    let mut marker = path.as_os_str().to_owned();
+    marker.push(".last-compacted");
+    let marker = Path::new(&marker);
+    match std::fs::read(marker) {
+        Ok(bytes) if bytes.len() == 8 => {
+            let mut nanos = [0; 8];
+            nanos.copy_from_slice(&bytes);
+            Some(u64::from_be_bytes(nanos) / 1_000_000_000)
+        }
+        Ok(_) => std::fs::metadata(marker)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        Err(_) => None,
+    }
+}
+
+struct Meta {
+    page_size: u64,
+    freelist: u64,
+    high_water_mark: u64,
+    txid: u64,
+}
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buf[at..at + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Parses the meta page at `offset`: page header, then magic, version, page size, flags,
+/// root bucket, freelist page, high water mark, txid and checksum
+fn read_meta(file: &mut File, offset: u64) -> Result<Option<Meta>, anyhow::Error> {
+    let mut buf = [0; 80];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buf)?;
+    let meta = &buf[PAGE_HEADER_SIZE as usize..];
+    if read_u32(meta, 0) != BOLT_MAGIC {
+        return Ok(None);
+    }
+    Ok(Some(Meta {
+        page_size: u64::from(read_u32(meta, 8)),
+        freelist: read_u64(meta, 32),
+        high_water_mark: read_u64(meta, 40),
+        txid: read_u64(meta, 48),
+    }))
+}
+
+fn reclaimable(path: &Path, size: u64) -> Result<u64, anyhow::Error> {
+    let mut file = File::open(path)?;
+    let first = read_meta(&mut file, 0)?
+        .ok_or_else(|| anyhow::anyhow!("{} is not a bolt database", path.display()))?;
+    // the meta pages alternate, the one with the higher txid is current
+    let meta = match read_meta(&mut file, first.page_size)? {
+        Some(second) if second.txid > first.txid => second,
+        _ => first,
+    };
+    let corrupt = || anyhow::anyhow!("{} has a corrupt meta page", path.display());
+    let used = meta
+        .high_water_mark
+        .checked_mul(meta.page_size)
+        .ok_or_else(corrupt)?;
+    let tail = size.saturating_sub(used);
+    if meta.freelist == NO_FREELIST {
+        return Ok(tail);
+    }
+    let freelist_offset = meta
+        .freelist
+        .checked_mul(meta.page_size)
+        .ok_or_else(corrupt)?;
+    let mut header = [0; PAGE_HEADER_SIZE as usize + 8];
+    file.seek(SeekFrom::Start(freelist_offset))?;
+    file.read_exact(&mut header)?;
+    let count = u64::from(u16::from_le_bytes([header[10], header[11]]));
+    // counts that overflow the header are stored as the first element
+    let free_pages = if count == 0xFFFF {
+        read_u64(&header, PAGE_HEADER_SIZE as usize)
+    } else {
+        count
+    };
+    free_pages
+        .checked_mul(meta.page_size)
+        .and_then(|free| free.checked_add(tail))
+        .ok_or_else(corrupt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_SIZE: usize = 4096;
+
+    fn meta_page(id: u64, freelist: u64, high_water_mark: u64, txid: u64) -> Vec<u8> {
+        let mut page = vec![0; PAGE_SIZE];
+        page[..8].copy_from_slice(&id.to_le_bytes());
+        let meta = &mut page[PAGE_HEADER_SIZE as usize..];
+        meta[..4].copy_from_slice(&BOLT_MAGIC.to_le_bytes());
+        meta[4..8].copy_from_slice(&2u32.to_le_bytes());
+        meta[8..12].copy_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+        meta[32..40].copy_from_slice(&freelist.to_le_bytes());
+        meta[40..48].copy_from_slice(&high_water_mark.to_le_bytes());
+        meta[48..56].copy_from_slice(&txid.to_le_bytes());
+        page
+    }
+
+    fn freelist_page(id: u64, free: &[u64]) -> Vec<u8> {
+        let mut page = vec![0; PAGE_SIZE];
+        page[..8].copy_from_slice(&id.to_le_bytes());
+        let mut ids = free.to_vec();
+        if free.len() >= 0xFFFF {
+            page[10..12].copy_from_slice(&0xFFFFu16.to_le_bytes());
+            ids.insert(0, free.len() as u64);
+        } else {
+            page[10..12].copy_from_slice(&(free.len() as u16).to_le_bytes());
+        }
+        for (i, id) in ids.iter().take(8).enumerate() {
+            let at = PAGE_HEADER_SIZE as usize + i * 8;
+            page[at..at + 8].copy_from_slice(&id.to_le_bytes());
+        }
+        page
+    }
+
+    /// Writes the pages to a scratch file and returns its reclaimable estimate
+    fn reclaimable_of(name: &str, pages: &[Vec<u8>]) -> Result<u64, anyhow::Error> {
+        let path = std::env::temp_dir().join(format!(
+            "health-check-disk-{}-{}.db",
+            name,
+            std::process::id()
+        ));
+        let data = pages.concat();
+        std::fs::write(&path, &data).unwrap();
+        let res = reclaimable(&path, data.len() as u64);
+        std::fs::remove_file(&path).unwrap();
+        res
+    }
+
+    fn page(id: u64) -> Vec<u8> {
+        let mut page = vec![0; PAGE_SIZE];
+        page[..8].copy_from_slice(&id.to_le_bytes());
+        page
+    }
+
+    #[test]
+    fn counts_free_pages_and_tail() {
+        // the second meta page is current: freelist on page 3, 5 pages in use, 2 preallocated
+        let pages = [
+            meta_page(0, 2, 4, 6),
+            meta_page(1, 3, 5, 7),
+            freelist_page(2, &[4]),
+            freelist_page(3, &[2, 4]),
+            page(4),
+            page(5),
+            page(6),
+        ];
+        assert_eq!(
+            reclaimable_of("current", &pages).unwrap(),
+            (2 + 2) * PAGE_SIZE as u64
+        );
+        // after the next commit the first one is
+        let mut pages = pages;
+        pages[0] = meta_page(0, 2, 4, 8);
+        assert_eq!(
+            reclaimable_of("alternate", &pages).unwrap(),
+            (1 + 3) * PAGE_SIZE as u64
+        );
+    }
+
+    #[test]
+    fn reads_overflowing_free_count() {
+        let free: Vec<u64> = (0..0x1_0000).collect();
+        let pages = [
+            meta_page(0, 2, 3, 1),
+            meta_page(1, 2, 3, 0),
+            freelist_page(2, &free),
+        ];
+        assert_eq!(
+            reclaimable_of("overflow", &pages).unwrap(),
+            0x1_0000 * PAGE_SIZE as u64
+        );
+    }
+
+    #[test]
+    fn without_freelist_only_counts_tail() {
+        let pages = [
+            meta_page(0, NO_FREELIST, 2, 1),
+            meta_page(1, NO_FREELIST, 2, 0),
+            page(2),
+        ];
+        assert_eq!(
+            reclaimable_of("nofreelist", &pages).unwrap(),
+            PAGE_SIZE as u64
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_files() {
+        // not a bolt database
+        assert!(reclaimable_of("garbage", &[page(0), page(1)]).is_err());
+        // shorter than a meta page
+        assert!(reclaimable_of("short", &[meta_page(0, 2, 3, 1)[..40].to_vec()]).is_err());
+        // freelist past the end of the file
+        let pages = [meta_page(0, 9, 3, 1), meta_page(1, 9, 3, 0), page(2)];
+        assert!(reclaimable_of("past-end", &pages).is_err());
+        // page ids so large the offsets overflow
+        let pages = [
+            meta_page(0, 1 << 60, 3, 1),
+            meta_page(1, 1 << 60, 3, 0),
+            page(2),
+        ];
+        assert!(reclaimable_of("overflow-offset", &pages).is_err());
+        let pages = [meta_page(0, 2, 1 << 60, 1), meta_page(1, 2, 1 << 60, 0)];
+        assert!(reclaimable_of("overflow-hwm", &pages).is_err());
+    }
+
+    #[test]
+    fn missing_database_has_no_usage() {
+        let path = std::env::temp_dir().join("health-check-disk-missing.db");
+        assert!(db_usage(&path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,310 @@
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::{Certificate, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::macaroon;
+
+const READONLY_MACAROON_PATH: &str = "/root/.lnd/data/chain/bitcoin/mainnet/readonly.macaroon";
+/// Baked by the configurator with only the permissions the health checks need
+const HEALTH_CHECK_MACAROON_PATH: &str = "/root/.lnd/start9/health-check.macaroon";
+pub const CHANNEL_BACKUP_PATH: &str = "/root/.lnd/data/chain/bitcoin/mainnet/channel.backup";
+/// Written by the configurator when it fails to unlock the wallet
+const UNLOCK_ERROR_PATH: &str = "/root/.lnd/start9/unlock-error";
+const TLS_CERT_PATH: &str = "/root/.lnd/tls.cert";
+const LND_CONF_PATH: &str = "/root/.lnd/lnd.conf";
+const CONFIG_PATH: &str = "/root/.lnd/start9/config.yaml";
+const REST_URL: &str = "https://lnd.embassy:8080";
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+pub const READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(serde::Deserialize, Debug)]
+pub struct LndGetInfoRes {
+    pub synced_to_chain: bool,
+    pub synced_to_graph: bool,
+    pub num_peers: u32,
+    pub block_height: u64,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WalletState {
+    /// the REST gateway omits enum fields holding their zero value
+    #[default]
+    NonExisting,
+    Locked,
+    Unlocked,
+    RpcActive,
+    ServerActive,
+    WaitingToStart,
+}
+#[derive(serde::Deserialize, Debug)]
+struct StateRes {
+    #[serde(default)]
+    state: WalletState,
+}
+
+/// The ways a call to the LND REST API can go wrong
+#[derive(Debug)]
+pub enum RestError {
+    /// nothing accepts connections on the REST port (yet)
+    NotListening(String),
+    /// LND presents a certificate that isn't tls.cert
+    TlsMismatch(String),
+    /// LND accepted the connection but did not answer in time
+    Timeout,
+    MacaroonRejected(String),
+    /// the macaroon on hand grants more than read access, or can't be inspected
+    MacaroonTooPowerful(String),
+    Http(StatusCode, String),
+    /// the response body is not what the endpoint should return
+    Malformed(String),
+}
+impl std::fmt::Display for RestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RestError::NotListening(e) => write!(f, "REST API is not listening: {}", e),
+            RestError::TlsMismatch(e) => write!(f, "TLS certificate mismatch: {}", e),
+            RestError::Timeout => write!(f, "REST API did not respond in time"),
+            RestError::MacaroonRejected(e) => write!(f, "Macaroon rejected: {}", e),
+            RestError::MacaroonTooPowerful(e) => {
+                write!(f, "Refusing to use the health check macaroon: {}", e)
+            }
+            RestError::Http(status, e) => write!(f, "REST API returned {}: {}", status, e),
+            RestError::Malformed(e) => write!(f, "Malformed response from REST API: {}", e),
+        }
+    }
+}
+impl std::error::Error for RestError {}
+impl From<reqwest::Error> for RestError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return RestError::Timeout;
+        }
+        // rustls reports handshake failures as InvalidData
+        let mut source = e.source();
+        while let Some(err) = source {
+            if let Some(io) = err.downcast_ref::<std::io::Error>() {
+                if io.kind() == std::io::ErrorKind::InvalidData {
+                    return RestError::TlsMismatch(io.to_string());
+                }
+            }
+            source = err.source();
+        }
+        RestError::NotListening(e.to_string())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ErrorRes {
+    message: String,
+}
+
+pub fn macaroon_exists() -> bool {
+    Path::new(READONLY_MACAROON_PATH).exists()
+}
+
+/// The dedicated health check macaroon if it has been baked yet, readonly.macaroon otherwise.
+/// Either way it must not grant anything beyond read access.
+fn read_macaroon() -> Result<Vec<u8>, anyhow::Error> {
+    let path = Path::new(HEALTH_CHECK_MACAROON_PATH);
+    let path = if path.exists() {
+        path
+    } else {
+        Path::new(READONLY_MACAROON_PATH)
+    };
+    let mac = std::fs::read(path)?;
+    let perms = macaroon::permissions(&mac)
+        .map_err(|e| RestError::MacaroonTooPowerful(format!("{}: {}", path.display(), e)))?;
+    let privileged: Vec<String> = perms
+        .into_iter()
+        .filter(|p| !p.ends_with(":read"))
+        .collect();
+    if !privileged.is_empty() {
+        return Err(RestError::MacaroonTooPowerful(format!(
+            "{} grants {}",
+            path.display(),
+            privileged.join(", ")
+        ))
+        .into());
+    }
+    Ok(mac)
+}
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// Every certificate in a PEM file. On StartOS tls.cert holds the whole chain, and rustls
+/// does not accept the leaf it starts with as a trust anchor.
+fn root_certificates(pem: &[u8]) -> Result<Vec<Certificate>, anyhow::Error> {
+    let certs = std::str::from_utf8(pem)?
+        .split_inclusive(PEM_END)
+        .filter_map(|block| block.find(PEM_BEGIN).map(|start| &block[start..]))
+        .map(|block| Ok(Certificate::from_pem(block.as_bytes())?))
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("no certificate in {}", TLS_CERT_PATH));
+    }
+    Ok(certs)
+}
+
+/// A client builder that only trusts the certificates in `pem`
+fn client_builder(pem: &[u8]) -> Result<ClientBuilder, anyhow::Error> {
+    let mut builder = Client::builder()
+        .tls_built_in_root_certs(false)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(READ_TIMEOUT);
+    for cert in root_certificates(pem)? {
+        builder = builder.add_root_certificate(cert);
+    }
+    Ok(builder)
+}
+
+/// A client that only trusts LND's own certificate chain
+fn client() -> Result<Client, anyhow::Error> {
+    Ok(client_builder(&std::fs::read(TLS_CERT_PATH)?)?.build()?)
+}
+
+/// GET an LND REST endpoint, authenticated with a read-only macaroon
+pub fn get<T: DeserializeOwned>(endpoint: &str) -> Result<T, anyhow::Error> {
+    request(endpoint, Some(read_macaroon()?))
+}
+
+fn request<T: DeserializeOwned>(endpoint: &str, mac: Option<Vec<u8>>) -> Result<T, anyhow::Error> {
+    let mut req = client()?.get(format!("{}{}", REST_URL, endpoint));
+    if let Some(mac) = mac {
+        req = req.header("Grpc-Metadata-macaroon", hex::encode_upper(mac));
+    }
+    let res = req.send().map_err(RestError::from)?;
+    let status = res.status();
+    let body = res.bytes().map_err(RestError::from)?;
+    if !status.is_success() {
+        let message = serde_json::from_slice::<ErrorRes>(&body)
+            .map(|e| e.message)
+            .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+        return Err(match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                RestError::MacaroonRejected(message)
+            }
+            // LND reports macaroon errors as a generic gRPC error
+            _ if message.contains("verification failed") || message.contains("macaroon") => {
+                RestError::MacaroonRejected(message)
+            }
+            _ => RestError::Http(status, message),
+        }
+        .into());
+    }
+    Ok(serde_json::from_slice(&body).map_err(|e| RestError::Malformed(e.to_string()))?)
+}
+
+/// The wallet state, which LND serves without authentication
+pub fn state() -> Result<WalletState, anyhow::Error> {
+    Ok(request::<StateRes>("/v1/state", None)?.state)
+}
+
+/// Why the configurator failed to unlock the wallet, if it did
+pub fn unlock_error() -> Option<String> {
+    std::fs::read_to_string(UNLOCK_ERROR_PATH)
+        .ok()
+        .map(|e| e.trim().to_owned())
+}
+
+/// getinfo, both parsed and as LND returned it
+pub fn get_info() -> Result<(LndGetInfoRes, serde_json::Value), anyhow::Error> {
+    let raw: serde_json::Value = get("/v1/getinfo")?;
+    let info =
+        serde_json::from_value(raw.clone()).map_err(|e| RestError::Malformed(e.to_string()))?;
+    Ok((info, raw))
+}
+
+/// Reads a value written to lnd.conf by the configurator
+pub fn conf_value(key: &str) -> Result<Option<String>, anyhow::Error> {
+    Ok(std::fs::read_to_string(LND_CONF_PATH)?
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| v.trim().to_owned()))
+}
+
+pub fn conf_flag(key: &str) -> Result<bool, anyhow::Error> {
+    Ok(conf_value(key)?.as_deref() == Some("true"))
+}
+
+/// The package config, as saved by StartOS
+pub fn config() -> Result<serde_yaml::Value, anyhow::Error> {
+    Ok(serde_yaml::from_reader(std::fs::File::open(CONFIG_PATH)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// A leaf for lnd.embassy, an intermediate and a root, in the order StartOS writes them
+    const CHAIN: &[u8] = include_bytes!("testdata/tls.cert");
+    const LEAF: &[u8] = include_bytes!("testdata/leaf.cert");
+    const KEY: &[u8] = include_bytes!("testdata/tls.key");
+
+    /// Answers a single HTTPS request with `{}`, presenting only the leaf like LND does
+    fn serve_once() -> SocketAddr {
+        let certs = rustls_pemfile::certs(&mut &LEAF[..])
+            .unwrap()
+            .into_iter()
+            .map(rustls::Certificate)
+            .collect();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut &KEY[..])
+            .unwrap()
+            .remove(0);
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, rustls::PrivateKey(key))
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            let conn = rustls::ServerConnection::new(Arc::new(config)).unwrap();
+            let mut tls = rustls::StreamOwned::new(conn, tcp);
+            let mut buf = [0; 1024];
+            if tls.read(&mut buf).is_ok() {
+                let _ = tls.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}");
+                let _ = tls.flush();
+            }
+        });
+        addr
+    }
+
+    fn get_from(pem: &[u8]) -> Result<serde_json::Value, anyhow::Error> {
+        let addr = serve_once();
+        Ok(client_builder(pem)?
+            .resolve("lnd.embassy", addr)
+            .build()?
+            .get(format!("https://lnd.embassy:{}/v1/getinfo", addr.port()))
+            .send()?
+            .json()?)
+    }
+
+    #[test]
+    fn trusts_every_certificate_in_the_chain() {
+        assert_eq!(root_certificates(CHAIN).unwrap().len(), 3);
+        assert_eq!(get_from(CHAIN).unwrap(), serde_json::json!({}));
+    }
+
+    #[test]
+    fn leaf_alone_is_no_trust_anchor() {
+        assert!(get_from(LEAF).is_err());
+    }
+
+    #[test]
+    fn rejects_files_without_certificates() {
+        assert!(root_certificates(b"").is_err());
+        assert!(root_certificates(KEY).is_err());
+    }
+}
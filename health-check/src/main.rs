@@ -1,92 +1,591 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(serde::Deserialize, Debug)]
-pub struct LndGetInfoRes {
-    synced_to_chain: bool,
-    synced_to_graph: bool,
-}
+mod bitcoind;
+mod channels;
+mod disk;
+mod lnd;
+mod macaroon;
+mod wtclient;
 
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "result", rename_all = "kebab-case")]
 pub enum HealthCheckResult {
     Success,
     Disabled,
-    Starting,
-    Loading { message: String },
-    Failure { error: String },
+    Starting {
+        message: Option<String>,
+    },
+    Loading {
+        message: String,
+    },
+    /// running, but something needs the user's attention
+    Warning {
+        message: String,
+    },
+    Failure {
+        error: String,
+    },
+}
+impl HealthCheckResult {
+    fn starting(message: impl Into<String>) -> Self {
+        HealthCheckResult::Starting {
+            message: Some(message.into()),
+        }
+    }
+    fn loading(message: impl Into<String>) -> Self {
+        HealthCheckResult::Loading {
+            message: message.into(),
+        }
+    }
+    fn warning(message: impl Into<String>) -> Self {
+        HealthCheckResult::Warning {
+            message: message.into(),
+        }
+    }
+    fn failure(error: impl Into<String>) -> Self {
+        HealthCheckResult::Failure {
+            error: error.into(),
+        }
+    }
+
+    /// The exit code StartOS maps back to this result
+    fn code(&self) -> i32 {
+        match self {
+            HealthCheckResult::Success => 0,
+            HealthCheckResult::Disabled => 59,
+            HealthCheckResult::Starting { .. } => 60,
+            // StartOS has no warning state, loading is the closest non-failure
+            HealthCheckResult::Loading { .. } | HealthCheckResult::Warning { .. } => 61,
+            HealthCheckResult::Failure { .. } => 1,
+        }
+    }
+
+    fn message(&self) -> Option<&str> {
+        match self {
+            HealthCheckResult::Starting { message } => message.as_deref(),
+            HealthCheckResult::Loading { message } | HealthCheckResult::Warning { message } => {
+                Some(message)
+            }
+            HealthCheckResult::Failure { error } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Everything a check found out, printed to stdout
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct HealthCheckReport {
+    check: String,
+    /// unix seconds
+    checked_at: u64,
+    #[serde(flatten)]
+    result: HealthCheckResult,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    details: BTreeMap<&'static str, Value>,
+    /// getinfo as returned by LND, if the check got that far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node_info: Option<Value>,
+}
+
+/// Collects details while a check runs
+#[derive(Default)]
+struct Context {
+    details: BTreeMap<&'static str, Value>,
+    node_info: Option<Value>,
+}
+impl Context {
+    fn detail(&mut self, key: &'static str, value: impl Serialize) {
+        self.details
+            .insert(key, serde_json::to_value(value).unwrap_or(Value::Null));
+    }
 }
 
 fn main() {
-    std::process::exit(match run_health_checks() {
-        Ok(result) => {
-            eprintln!("{}", result.message.unwrap_or_default());
-            result.code
-        }
-        Err(err) => {
-            eprintln!("{}", err);
-            1
-        }
-    });
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct HealthCheckRes {
-    pub code: i32,
-    pub message: Option<String>,
-}
-
-fn run_health_checks() -> Result<HealthCheckRes, anyhow::Error> {
-    while !Path::new("/root/.lnd/data/chain/bitcoin/mainnet/admin.macaroon").exists() {
-        return Ok(HealthCheckRes {
-            code: 60,
-            message: None,
-        });
-    }
-
-    let mac = std::fs::read(Path::new(
-        "/root/.lnd/data/chain/bitcoin/mainnet/admin.macaroon",
-    ))?;
-
-    let mac_encoded = hex::encode_upper(mac);
-    let node_info: Result<LndGetInfoRes, anyhow::Error> = {
-        serde_json::from_slice(
-            &std::process::Command::new("curl")
-                .arg("--no-progress-meter")
-                .arg("--header")
-                .arg(format!("Grpc-Metadata-macaroon: {}", mac_encoded))
-                .arg("--cacert")
-                .arg("/root/.lnd/tls.cert")
-                .arg("https://lnd.embassy:8080/v1/getinfo")
-                .output()?
-                .stdout,
-        )
-        .map_err(|e| e.into())
+    let mut check = None;
+    let mut json = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ => check = Some(arg),
+        }
+    }
+    let check = check.unwrap_or_else(|| "synced".to_owned());
+    let mut ctx = Context::default();
+    let result = run_health_check(&check, &mut ctx)
+        .unwrap_or_else(|e| HealthCheckResult::failure(e.to_string()));
+    // StartOS reads the message of a non-successful check from stderr
+    if let Some(message) = result.message() {
+        eprintln!("{}", message);
+    }
+    let code = result.code();
+    let report = HealthCheckReport {
+        check,
+        checked_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        result,
+        details: ctx.details,
+        node_info: ctx.node_info,
+    };
+    let output = if json {
+        serde_json::to_string_pretty(&report).map_err(anyhow::Error::from)
+    } else {
+        serde_yaml::to_string(&report).map_err(anyhow::Error::from)
+    };
+    match output {
+        Ok(output) => println!("{}", output),
+        Err(e) => eprintln!("Error serializing report: {}", e),
+    }
+    std::process::exit(code);
+}
+
+fn run_health_check(check: &str, ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    match check {
+        "synced" => check_synced(ctx),
+        "rest" => check_rest(ctx),
+        "wallet" => check_wallet(ctx),
+        "chain-sync" => check_chain_sync(ctx),
+        "graph-sync" => check_graph_sync(ctx),
+        "peers" => check_peers(ctx),
+        "backend" => check_backend(ctx),
+        "watchtower" => check_watchtower(ctx),
+        "watchtower-client" => check_watchtower_client(ctx),
+        "backup" => check_backup(ctx),
+        "channels" => check_channels(ctx),
+        "disk" => check_disk(ctx),
+        other => Err(anyhow::anyhow!("Unknown health check: {}", other)),
+    }
+}
+
+/// Maps a failed REST call to a result: Starting while LND is still coming up, Failure otherwise
+fn rest_error(e: anyhow::Error) -> HealthCheckResult {
+    match e.downcast_ref::<lnd::RestError>() {
+        Some(lnd::RestError::NotListening(_)) => HealthCheckResult::starting(e.to_string()),
+        Some(lnd::RestError::Http(_, message))
+            if message.contains("starting") || message.contains("waiting to start") =>
+        {
+            HealthCheckResult::starting(e.to_string())
+        }
+        Some(_) => HealthCheckResult::failure(e.to_string()),
+        // assets like tls.cert are unavailable while booting up
+        None => HealthCheckResult::starting(e.to_string()),
+    }
+}
+
+/// Reports how far LND has come, unless it is fully up and ready for authenticated calls
+fn wallet_not_ready(ctx: &mut Context) -> Option<HealthCheckResult> {
+    let state = match lnd::state() {
+        Ok(state) => state,
+        Err(e) => return Some(rest_error(e)),
+    };
+    ctx.detail("wallet-state", format!("{:?}", state));
+    Some(match state {
+        lnd::WalletState::NonExisting => {
+            HealthCheckResult::loading("Waiting for the wallet to be created")
+        }
+        lnd::WalletState::Locked => match lnd::unlock_error() {
+            Some(e) => HealthCheckResult::failure(format!("Wallet failed to unlock: {}", e)),
+            None => HealthCheckResult::loading("Wallet is locked, waiting for it to be unlocked"),
+        },
+        lnd::WalletState::Unlocked => {
+            HealthCheckResult::loading("Wallet unlocked, starting the RPC server")
+        }
+        lnd::WalletState::RpcActive => {
+            HealthCheckResult::loading("RPC server is active, waiting for LND to finish starting")
+        }
+        lnd::WalletState::WaitingToStart => HealthCheckResult::starting("Waiting to start"),
+        lnd::WalletState::ServerActive if !lnd::macaroon_exists() => {
+            HealthCheckResult::starting("Waiting for macaroons to be created")
+        }
+        lnd::WalletState::ServerActive => return None,
+    })
+}
+
+/// getinfo, or the result to report if LND can't answer it
+fn node_info(ctx: &mut Context) -> Result<lnd::LndGetInfoRes, HealthCheckResult> {
+    if let Some(res) = wallet_not_ready(ctx) {
+        return Err(res);
+    }
+    let (info, raw) = lnd::get_info().map_err(rest_error)?;
+    ctx.node_info = Some(raw);
+    Ok(info)
+}
+
+fn check_synced(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    let r = match node_info(ctx) {
+        Ok(r) => r,
+        Err(res) => return Ok(res),
+    };
+    Ok(match () {
+        () if r.synced_to_graph && r.synced_to_chain => HealthCheckResult::Success,
+        () if !r.synced_to_chain && r.synced_to_graph => {
+            HealthCheckResult::loading("Syncing to chain")
+        }
+        () if !r.synced_to_graph && r.synced_to_chain => {
+            HealthCheckResult::loading("Syncing to graph")
+        }
+        () => HealthCheckResult::loading("Syncing to graph and chain"),
+    })
+}
+
+fn check_rest(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    Ok(match node_info(ctx) {
+        Ok(_) => HealthCheckResult::Success,
+        Err(res) => res,
+    })
+}
+
+fn check_wallet(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    Ok(wallet_not_ready(ctx).unwrap_or(HealthCheckResult::Success))
+}
+
+/// Share of channels that may be inactive before the channels check warns, unless configured otherwise
+const DEFAULT_MAX_INACTIVE_CHANNELS_PERCENT: u64 = 50;
+
+/// Free space (GB) on the LND volume below which the disk check warns or fails, unless configured otherwise
+const DEFAULT_DISK_FREE_WARN_GB: u64 = 10;
+const DEFAULT_DISK_FREE_FAIL_GB: u64 = 2;
+
+/// Blocks LND may trail Bitcoin Core by before Chain Sync fails, unless configured otherwise
+const DEFAULT_MAX_BLOCK_LAG: u64 = 6;
+
+fn check_chain_sync(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    let bitcoind_height = match lnd::conf_value("bitcoin.node")?.as_deref() {
+        // an unreachable Bitcoin Core is reported by the backend check
+        Some("bitcoind") => bitcoind::call::<u64>("getblockcount").ok(),
+        _ => None,
     };
+    let max_lag = lnd::config()
+        .ok()
+        .and_then(|c| c["advanced"]["health-check"]["max-block-lag"].as_u64())
+        .unwrap_or(DEFAULT_MAX_BLOCK_LAG);
+    let r = match node_info(ctx) {
+        Ok(r) => r,
+        Err(res) => return Ok(res),
+    };
+    let lag = bitcoind_height.map_or(0, |h| h.saturating_sub(r.block_height));
+    ctx.detail("block-height", r.block_height);
+    ctx.detail("bitcoind-block-height", bitcoind_height);
+    ctx.detail("block-lag", lag);
+    ctx.detail("max-block-lag", max_lag);
+    Ok(match () {
+        () if lag > max_lag => HealthCheckResult::failure(format!(
+            "{} blocks behind Bitcoin Core (more than the allowed {})",
+            lag, max_lag
+        )),
+        () if !r.synced_to_chain && lag > 0 => {
+            HealthCheckResult::loading(format!("Syncing to chain, {} blocks behind", lag))
+        }
+        () if !r.synced_to_chain => HealthCheckResult::loading("Syncing to chain"),
+        () if lag > 0 => HealthCheckResult::loading(format!("{} blocks behind", lag)),
+        () => HealthCheckResult::Success,
+    })
+}
 
-    match node_info {
-        Ok(r) => match () {
-            () if r.synced_to_graph && r.synced_to_chain => Ok(HealthCheckRes {
-                code: 0,
-                message: None,
-            }),
-            () if !r.synced_to_chain && r.synced_to_graph => Ok(HealthCheckRes {
-                code: 61,
-                message: Some("Syncing to chain".to_string()),
-            }),
-            () if !r.synced_to_graph && r.synced_to_chain => Ok(HealthCheckRes {
-                code: 61,
-                message: Some("Syncing to graph".to_string()),
-            }),
-            () => Ok(HealthCheckRes {
-                code: 61,
-                message: Some("Syncing to graph and chain".to_string()),
-            }),
+fn check_graph_sync(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    Ok(match node_info(ctx) {
+        Ok(r) if r.synced_to_graph => HealthCheckResult::Success,
+        Ok(_) => HealthCheckResult::loading("Syncing to graph"),
+        Err(res) => res,
+    })
+}
+
+fn check_peers(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    Ok(match node_info(ctx) {
+        Ok(r) => match r.num_peers {
+            0 if r.synced_to_chain => HealthCheckResult::failure("No peers connected"),
+            0 => HealthCheckResult::loading("Waiting for peers"),
+            _ => HealthCheckResult::Success,
         },
-        Err(e) => {
-            // this will error if assets are unavailble while booting up, so use exit code for Starting
-            Ok(HealthCheckRes {
-                code: 60,
-                message: Some(e.to_string()),
-            })
+        Err(res) => res,
+    })
+}
+
+fn check_backend(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    let node = lnd::conf_value("bitcoin.node")?;
+    ctx.detail("backend", &node);
+    match node.as_deref() {
+        Some("bitcoind") => match bitcoind::call::<Value>("getblockchaininfo") {
+            Ok(info) => {
+                ctx.detail("blockchain-info", info);
+                Ok(HealthCheckResult::Success)
+            }
+            Err(e) => Ok(HealthCheckResult::failure(format!(
+                "Unable to reach Bitcoin Core: {}",
+                e
+            ))),
+        },
+        Some("neutrino") => {
+            let bitcoind_type = lnd::config()?["bitcoind"]["type"].clone();
+            if bitcoind_type.as_str() == Some("internal") {
+                Ok(HealthCheckResult::loading(
+                    "Using Neutrino until Bitcoin Core finishes its initial block download",
+                ))
+            } else {
+                Ok(HealthCheckResult::Success)
+            }
+        }
+        _ => Ok(HealthCheckResult::Starting { message: None }),
+    }
+}
+
+#[derive(Deserialize)]
+struct TowerServerInfo {
+    #[serde(default)]
+    uris: Vec<String>,
+}
+
+fn check_watchtower(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    if !lnd::conf_flag("watchtower.active")? {
+        return Ok(HealthCheckResult::Disabled);
+    }
+    if let Some(res) = wallet_not_ready(ctx) {
+        return Ok(res);
+    }
+    Ok(match lnd::get::<TowerServerInfo>("/v2/watchtower/server") {
+        Ok(info) if info.uris.is_empty() => {
+            HealthCheckResult::loading("Waiting for the watchtower server to publish its URIs")
+        }
+        Ok(info) => {
+            ctx.detail("uris", info.uris);
+            HealthCheckResult::Success
+        }
+        Err(e) => rest_error(e),
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct TowerReport {
+    addresses: Vec<String>,
+    configured: bool,
+    active_session_candidate: bool,
+    sessions: u32,
+    backups: u32,
+    pending_backups: u32,
+}
+
+fn check_watchtower_client(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    if !lnd::conf_flag("wtclient.active")? {
+        return Ok(HealthCheckResult::Disabled);
+    }
+    if let Some(res) = wallet_not_ready(ctx) {
+        return Ok(res);
+    }
+    let towers = match wtclient::towers() {
+        Ok(towers) => towers,
+        Err(e) => return Ok(rest_error(e)),
+    };
+    let stats = match wtclient::stats() {
+        Ok(stats) => stats,
+        Err(e) => return Ok(rest_error(e)),
+    };
+    let configured = wtclient::configured_towers()?;
+    let history = wtclient::record_pending(stats.num_pending_backups)?;
+    let report: BTreeMap<String, TowerReport> = towers
+        .iter()
+        .map(|t| {
+            let pubkey = t.pubkey_hex();
+            let sessions = t.session_info.iter().flat_map(|s| &s.sessions);
+            let report = TowerReport {
+                addresses: t.addresses.clone(),
+                configured: configured.contains(&pubkey),
+                active_session_candidate: t.active(),
+                sessions: t.session_info.iter().map(|s| s.num_sessions).sum(),
+                backups: sessions.clone().map(|s| s.num_backups).sum(),
+                pending_backups: sessions.map(|s| s.num_pending_backups).sum(),
+            };
+            (pubkey, report)
+        })
+        .collect();
+    ctx.detail("towers", &report);
+    ctx.detail("stats", &stats);
+    ctx.detail("pending-backups-history", &history);
+
+    if configured.is_empty() {
+        return Ok(HealthCheckResult::loading("No watchtowers are configured"));
+    }
+    let reachable = towers
+        .iter()
+        .filter(|t| t.reachable() && configured.contains(&t.pubkey_hex()))
+        .count();
+    if reachable == 0 {
+        let open_channels = match channels::list() {
+            Ok(open) => open.len(),
+            Err(e) => return Ok(rest_error(e)),
+        };
+        ctx.detail("open-channels", open_channels);
+        if !wtclient::sessions_expected(open_channels, &stats) {
+            return Ok(HealthCheckResult::loading(
+                "No channel updates to back up yet, LND negotiates a session with a watchtower once there are",
+            ));
+        }
+        return Ok(HealthCheckResult::failure(format!(
+            "None of the {} configured watchtowers could be reached",
+            configured.len()
+        )));
+    }
+    let mut problems = Vec::new();
+    let inactive: Vec<&str> = report
+        .iter()
+        .filter(|(_, t)| t.configured && !t.active_session_candidate)
+        .map(|(pubkey, _)| pubkey.as_str())
+        .collect();
+    if !inactive.is_empty() {
+        problems.push(format!("no active session with {}", inactive.join(", ")));
+    }
+    if stats.num_failed_backups > 0 {
+        problems.push(format!("{} backups failed", stats.num_failed_backups));
+    }
+    if wtclient::pending_growing(&history) {
+        problems.push(format!(
+            "pending backups keep growing ({})",
+            stats.num_pending_backups
+        ));
+    }
+    if problems.is_empty() {
+        Ok(HealthCheckResult::Success)
+    } else {
+        Ok(HealthCheckResult::warning(format!(
+            "{} of {} watchtowers reachable: {}",
+            reachable,
+            configured.len(),
+            problems.join("; ")
+        )))
+    }
+}
+
+fn check_backup(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    if let Some(res) = wallet_not_ready(ctx) {
+        return Ok(res);
+    }
+    match std::fs::metadata(Path::new(lnd::CHANNEL_BACKUP_PATH)) {
+        Ok(m) if m.len() > 0 => {
+            ctx.detail("size", m.len());
+            Ok(HealthCheckResult::Success)
         }
+        Ok(_) => Ok(HealthCheckResult::failure("Channel backup file is empty")),
+        Err(_) => Ok(HealthCheckResult::failure("Channel backup file not found")),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PendingCloseReport {
+    channel_point: String,
+    remote_pubkey: String,
+    closing_txid: String,
+    limbo_balance: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    maturity_height: Option<u32>,
+}
+
+fn check_channels(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    let max_inactive = lnd::config()
+        .ok()
+        .and_then(|c| c["advanced"]["health-check"]["max-inactive-channels"].as_u64())
+        .unwrap_or(DEFAULT_MAX_INACTIVE_CHANNELS_PERCENT);
+    if let Err(res) = node_info(ctx) {
+        return Ok(res);
+    }
+    let (open, pending) = match channels::list().and_then(|c| Ok((c, channels::pending()?))) {
+        Ok(res) => res,
+        Err(e) => return Ok(rest_error(e)),
+    };
+    let inactive: Vec<&channels::Channel> = open.iter().filter(|c| !c.active).collect();
+    let waiting_close: Vec<PendingCloseReport> = pending
+        .waiting_close_channels
+        .into_iter()
+        .map(|c| PendingCloseReport {
+            channel_point: c.channel.channel_point,
+            remote_pubkey: c.channel.remote_node_pub,
+            closing_txid: c.closing_txid,
+            limbo_balance: c.limbo_balance,
+            maturity_height: None,
+        })
+        .collect();
+    let overdue_force_closes: Vec<PendingCloseReport> = pending
+        .pending_force_closing_channels
+        .into_iter()
+        .filter(|c| c.maturity_height > 0 && c.blocks_til_maturity < 0)
+        .map(|c| PendingCloseReport {
+            channel_point: c.channel.channel_point,
+            remote_pubkey: c.channel.remote_node_pub,
+            closing_txid: c.closing_txid,
+            limbo_balance: c.limbo_balance,
+            maturity_height: Some(c.maturity_height),
+        })
+        .collect();
+    ctx.detail("open-channels", open.len());
+    ctx.detail(
+        "inactive-channels",
+        inactive
+            .iter()
+            .map(|c| (c.channel_point.as_str(), c.remote_pubkey.as_str()))
+            .collect::<BTreeMap<_, _>>(),
+    );
+    ctx.detail("max-inactive-channels", max_inactive);
+    ctx.detail("waiting-close-channels", &waiting_close);
+    ctx.detail("overdue-force-closes", &overdue_force_closes);
+
+    let mut problems = Vec::new();
+    let inactive_percent = (inactive.len() * 100).checked_div(open.len()).unwrap_or(0) as u64;
+    if inactive_percent > max_inactive {
+        problems.push(format!(
+            "{} of {} channels are inactive",
+            inactive.len(),
+            open.len()
+        ));
+    }
+    if !overdue_force_closes.is_empty() {
+        problems.push(format!(
+            "{} force-closed channels are past their maturity height but not swept",
+            overdue_force_closes.len()
+        ));
+    }
+    if !waiting_close.is_empty() {
+        problems.push(format!(
+            "{} channels are waiting for their commitment transaction to confirm",
+            waiting_close.len()
+        ));
+    }
+    if problems.is_empty() {
+        Ok(HealthCheckResult::Success)
+    } else {
+        Ok(HealthCheckResult::warning(problems.join("; ")))
+    }
+}
+
+fn check_disk(ctx: &mut Context) -> Result<HealthCheckResult, anyhow::Error> {
+    let config = lnd::config().ok();
+    let threshold = |key: &str, default: u64| {
+        config
+            .as_ref()
+            .and_then(|c| c["advanced"]["health-check"][key].as_u64())
+            .unwrap_or(default)
+            * 1_000_000_000
+    };
+    let warn = threshold("disk-free-warn", DEFAULT_DISK_FREE_WARN_GB);
+    let fail = threshold("disk-free-fail", DEFAULT_DISK_FREE_FAIL_GB);
+    let usage = disk::DiskUsage::measure()?;
+    if let Err(e) = usage.store() {
+        eprintln!("Error writing disk usage: {}", e);
     }
+    ctx.detail("usage", &usage);
+    let free_gb = usage.free as f64 / 1e9;
+    Ok(if usage.free < fail {
+        HealthCheckResult::failure(format!(
+            "Only {:.1} GB free on the LND volume, LND risks corrupting its database when the disk fills up",
+            free_gb
+        ))
+    } else if usage.free < warn {
+        HealthCheckResult::warning(format!("Only {:.1} GB free on the LND volume", free_gb))
+    } else {
+        HealthCheckResult::Success
+    })
 }
@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lnd;
+
+/// Pending backup counts seen by previous runs of the check
+const HISTORY_PATH: &str = "/root/.lnd/start9/wtclient-pending.yaml";
+/// How many runs the pending count has to keep growing over before it is reported
+const HISTORY_LEN: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct TowerSession {
+    #[serde(default)]
+    pub num_backups: u32,
+    #[serde(default)]
+    pub num_pending_backups: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TowerSessionInfo {
+    #[serde(default)]
+    pub active_session_candidate: bool,
+    #[serde(default)]
+    pub num_sessions: u32,
+    #[serde(default)]
+    pub sessions: Vec<TowerSession>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Tower {
+    /// base64, as the REST gateway encodes bytes
+    pub pubkey: String,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub session_info: Vec<TowerSessionInfo>,
+}
+impl Tower {
+    pub fn pubkey_hex(&self) -> String {
+        base64::decode(&self.pubkey)
+            .map(hex::encode)
+            .unwrap_or_else(|_| self.pubkey.clone())
+    }
+
+    pub fn active(&self) -> bool {
+        self.session_info.iter().any(|s| s.active_session_candidate)
+    }
+
+    /// LND does not expose tower connectivity, so a tower it never negotiated a session with counts as unreachable
+    pub fn reachable(&self) -> bool {
+        self.session_info.iter().any(|s| s.num_sessions > 0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TowerList {
+    #[serde(default)]
+    towers: Vec<Tower>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Stats {
+    #[serde(default)]
+    pub num_backups: u32,
+    #[serde(default)]
+    pub num_pending_backups: u32,
+    #[serde(default)]
+    pub num_failed_backups: u32,
+    #[serde(default)]
+    pub num_sessions_acquired: u32,
+    #[serde(default)]
+    pub num_sessions_exhausted: u32,
+}
+
+pub fn towers() -> Result<Vec<Tower>, anyhow::Error> {
+    Ok(lnd::get::<TowerList>("/v2/watchtower/client?include_sessions=true")?.towers)
+}
+
+pub fn stats() -> Result<Stats, anyhow::Error> {
+    lnd::get("/v2/watchtower/client/stats")
+}
+
+/// Pubkeys (hex) of the towers listed in the config
+pub fn configured_towers() -> Result<Vec<String>, anyhow::Error> {
+    Ok(
+        lnd::config()?["watchtowers"]["wt-client"]["add-watchtowers"]
+            .as_sequence()
+            .into_iter()
+            .flatten()
+            .filter_map(|uri| uri.as_str())
+            .filter_map(|uri| uri.split('@').next())
+            .map(|pubkey| pubkey.trim().to_lowercase())
+            .collect(),
+    )
+}
+
+/// LND only negotiates a session once it has channel updates to back up, so a tower
+/// without one is only a problem while updates are waiting for it
+pub fn sessions_expected(open_channels: usize, stats: &Stats) -> bool {
+    open_channels > 0 && stats.num_pending_backups > 0
+}
+
+/// Records `pending` and returns the recent history, oldest first
+pub fn record_pending(pending: u32) -> Result<Vec<u32>, anyhow::Error> {
+    let path = Path::new(HISTORY_PATH);
+    let mut history: Vec<u32> = if path.exists() {
+        serde_yaml::from_reader(std::fs::File::open(path)?).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    history.push(pending);
+    if history.len() > HISTORY_LEN {
+        history.drain(..history.len() - HISTORY_LEN);
+    }
+    let tmp = path.with_extension("yaml.tmp");
+    std::fs::write(&tmp, serde_yaml::to_string(&history)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(history)
+}
+
+/// Whether the pending count went up on every one of the last runs
+pub fn pending_growing(history: &[u32]) -> bool {
+    history.len() == HISTORY_LEN && history.windows(2).all(|w| w[1] > w[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_only_expected_with_pending_backups() {
+        let idle = Stats::default();
+        let waiting = Stats {
+            num_pending_backups: 3,
+            ..Stats::default()
+        };
+        let done = Stats {
+            num_backups: 10,
+            ..Stats::default()
+        };
+        assert!(!sessions_expected(0, &idle));
+        assert!(!sessions_expected(2, &idle));
+        assert!(!sessions_expected(2, &done));
+        assert!(!sessions_expected(0, &waiting));
+        assert!(sessions_expected(2, &waiting));
+    }
+}
@@ -1,11 +1,18 @@
 import { matches } from "../deps.ts";
 
-const { shape, number, string, boolean } = matches;
+const { shape, number, string, boolean, arrayOf } = matches;
+
+export const matchHybrid = shape({
+  enabled: string,
+  "external-hosts": arrayOf(string),
+  "p2p-port": number,
+}, ["external-hosts", "p2p-port"]);
 
 export const matchTor = shape({
   "use-tor-only": boolean,
   "stream-isolation": boolean,
-});
+  hybrid: matchHybrid,
+}, ["hybrid"]);
 
 export const matchBitcoind = shape({
   type: string,
@@ -45,6 +52,13 @@ export const matchSweeperOptions = shape({
   "sweeper-budget-nodeadlinehtlcratio": number,
 });
 
+export const matchHealthCheck = shape({
+  "max-block-lag": number,
+  "max-inactive-channels": number,
+  "disk-free-warn": number,
+  "disk-free-fail": number,
+});
+
 export const matchAdvanced2 = shape({
   "debug-level": string,
   "db-bolt-no-freelist-sync": boolean,
@@ -66,7 +80,51 @@ export const matchAdvanced2 = shape({
   "gc-canceled-invoices-on-startup": boolean,
   bitcoin: matchBitcoin,
   "sweeper": matchSweeperOptions,
-}, ["recovery-window"]);
+  "rpc-interface": string,
+  "health-check": matchHealthCheck,
+}, ["recovery-window", "rpc-interface", "health-check"]);
+
+export const matchMacaroonProfile = shape({
+  name: string,
+  preset: string,
+  "custom-permissions": arrayOf(string),
+  timeout: number,
+  "ip-lock": string,
+}, ["timeout", "ip-lock"]);
+
+export const matchMacaroons = shape({
+  "publish-default-macaroons": boolean,
+  profiles: arrayOf(matchMacaroonProfile),
+});
+
+export const matchWtClient = shape({
+  enabled: string,
+  "add-watchtowers": arrayOf(string),
+  "sweep-fee-rate": number,
+  "session-close-range": number,
+  "max-tasks-in-mem-queue": number,
+  "max-updates": number,
+}, [
+  "add-watchtowers",
+  "sweep-fee-rate",
+  "session-close-range",
+  "max-tasks-in-mem-queue",
+  "max-updates",
+]);
+
+export const matchWtServerSettings = shape({
+  "listen-port": number,
+  "external-hosts": arrayOf(string),
+  "read-timeout": number,
+  "write-timeout": number,
+  "tower-dir": string,
+}, ["read-timeout", "write-timeout", "tower-dir"]);
+
+export const matchWatchtowers = shape({
+  "wt-server": boolean,
+  "wt-server-settings": matchWtServerSettings,
+  "wt-client": matchWtClient,
+}, ["wt-server-settings"]);
 
 export const matchRoot = shape({
   alias: string,
@@ -80,6 +138,8 @@ export const matchRoot = shape({
   bitcoind: matchBitcoind,
   autopilot: matchAutopilot,
   advanced: matchAdvanced2,
+  macaroons: matchMacaroons,
+  watchtowers: matchWatchtowers,
   "control-tor-address": string,
   "peer-tor-address": string,
   "watchtower-tor-address": string,
@@ -90,6 +150,8 @@ export const matchRoot = shape({
   "watchtower-tor-address",
   "peer-tor-address",
   "control-tor-address",
+  "macaroons",
+  "watchtowers",
 ]);
 
 export type Root = typeof matchRoot._TYPE;
@@ -99,3 +161,5 @@ export type Autopilot = typeof matchAutopilot._TYPE;
 export type Advanced = typeof matchAdvanced._TYPE;
 export type Advanced2 = typeof matchAdvanced2._TYPE;
 export type Bitcoin = typeof matchBitcoin._TYPE;
+export type Macaroons = typeof matchMacaroons._TYPE;
+export type Watchtowers = typeof matchWatchtowers._TYPE;
@@ -111,6 +111,51 @@ export const getConfig: T.ExpectedExports.getConfig = compat.getConfig({
           "Enable Tor stream isolation by randomizing user credentials for each connection. With this mode active, each connection will use a new circuit. This means that multiple applications (other than lnd) using Tor won't be mixed in with lnd's traffic.\nThis option may not be used when 'Use Tor for all traffic' is disabled, since direct connections compromise source IP privacy by default.",
         "default": false,
       },
+      "hybrid": {
+        "type": "union",
+        "name": "Clearnet (Hybrid Mode)",
+        "description":
+          "Also accept and advertise clearnet connections, next to your Tor address. Routing nodes are much more reliable when peers can reach them over clearnet. This reveals your public IP address to the network and needs 'Use Tor for all traffic' and 'Stream Isolation' disabled; while either is enabled, hybrid mode is ignored.\nUPnP/NAT-PMP is not supported: LND cannot use NAT traversal while it advertises your Tor address, so you must forward the P2P Port on your router manually.",
+        tag: {
+          id: "enabled",
+          name: "Hybrid Mode",
+          description: "Enable or disable clearnet reachability",
+          "variant-names": {
+            disabled: "Disabled",
+            enabled: "Enabled",
+          },
+        },
+        "default": "disabled",
+        variants: {
+          disabled: {},
+          enabled: {
+            "external-hosts": {
+              "type": "list",
+              "name": "External Hosts",
+              "description":
+                "Public addresses to advertise, as host or host:port. Static IPv4 or bracketed IPv6 addresses are advertised as they are; domain names are re-resolved by LND, so dynamic DNS works. The port defaults to the P2P Port. StartOS does not forward clearnet traffic, so you need to forward the port on your router yourself.",
+              "range": "[0,*)",
+              "subtype": "string",
+              "spec": {
+                "masked": false,
+                "copyable": true,
+                "placeholder": "node.example.com:9735",
+              },
+              "default": Array<string>(),
+            },
+            "p2p-port": {
+              "type": "number",
+              "name": "P2P Port",
+              "description":
+                "Additional port LND listens on for clearnet peers. The Tor address always uses port 9735.",
+              "nullable": false,
+              "range": "[1,65535]",
+              "integral": true,
+              "default": 9735,
+            },
+          },
+        },
+      },
     },
   },
   "bitcoind": {
@@ -262,6 +307,67 @@ export const getConfig: T.ExpectedExports.getConfig = compat.getConfig({
           "Allow other nodes to find your watchtower server on the network.",
         "default": false,
       },
+      "wt-server-settings": {
+        "type": "object",
+        "name": "Watchtower Server Settings",
+        "description": "Only used when the Watchtower Server is enabled.",
+        "spec": {
+          "listen-port": {
+            "type": "number",
+            "name": "Clearnet Listen Port",
+            "description":
+              "Additional port the tower listens on for clearnet clients. The Tor address always uses port 9911. StartOS does not forward clearnet traffic, so you need to forward this port to your server yourself.",
+            "nullable": false,
+            "range": "[1,65535]",
+            "integral": true,
+            "default": 9911,
+          },
+          "external-hosts": {
+            "type": "list",
+            "name": "External Hosts",
+            "description":
+              "Extra public addresses to advertise in your tower URIs, as host or host:port. IPv4, bracketed IPv6, domain names and v3 onion addresses are accepted; the port defaults to the Clearnet Listen Port. LND resolves domain names once at startup.",
+            "range": "[0,*)",
+            "subtype": "string",
+            "spec": {
+              "masked": false,
+              "copyable": true,
+              "placeholder": "tower.example.com:9911",
+            },
+            "default": Array<string>(),
+          },
+          "read-timeout": {
+            "type": "number",
+            "name": "Read Timeout",
+            "description":
+              "How long the tower waits for a client message before dropping the connection. Leave empty for LND's default of 15 seconds.",
+            "nullable": true,
+            "range": "[1,*)",
+            "integral": true,
+            "units": "seconds",
+          },
+          "write-timeout": {
+            "type": "number",
+            "name": "Write Timeout",
+            "description":
+              "How long the tower waits to send a message to a client before dropping the connection. Leave empty for LND's default of 15 seconds.",
+            "nullable": true,
+            "range": "[1,*)",
+            "integral": true,
+            "units": "seconds",
+          },
+          "tower-dir": {
+            "type": "string",
+            "name": "Tower Data Directory",
+            "description":
+              "Where the tower keeps its database and keys, relative to LND's data directory. Changing it gives the tower a new identity and drops all client sessions. Leave empty for the default, data/watchtower.",
+            "nullable": true,
+            "pattern": "^[A-Za-z0-9_-]+(/[A-Za-z0-9_-]+)*$",
+            "pattern-description":
+              "A relative path made of letters, digits, dashes and underscores",
+          },
+        },
+      },
       "wt-client": {
         "type": "union",
         "name": "Enable Watchtower Client",
@@ -285,7 +391,7 @@ export const getConfig: T.ExpectedExports.getConfig = compat.getConfig({
               "type": "list",
               "name": "Add Watchtowers",
               "description":
-                "Add URIs of Watchtowers to connect to.",
+                "Add URIs of Watchtowers to connect to, as pubkey@host:port. The pubkey is the tower's 33-byte compressed public key in hex; the host can be an IPv4 address, a bracketed IPv6 address, a domain name or a v3 onion address. The port defaults to 9911. Towers removed from this list are removed from the watchtower client on restart.",
               "range": "[1,*)",
               "subtype": "string",
               "spec": {
@@ -296,11 +402,138 @@ export const getConfig: T.ExpectedExports.getConfig = compat.getConfig({
               },
               "default": Array<string>(),
             },
+            "sweep-fee-rate": {
+              "type": "number",
+              "name": "Sweep Fee Rate",
+              "description":
+                "Fee rate the tower uses for justice transactions it broadcasts on your behalf. Higher rates confirm faster but leave less of the swept amount, and outputs too small to cover the fee are not backed up. Leave empty for LND's default of 10 sat/vbyte.",
+              "nullable": true,
+              "range": "[1,*)",
+              "integral": true,
+              "units": "sat/vbyte",
+            },
+            "session-close-range": {
+              "type": "number",
+              "name": "Session Close Range",
+              "description":
+                "Exhausted sessions are closed with the tower at a random block within this many blocks after all their channels have closed, so the tower cannot link sessions to channel closes. Leave empty for LND's default of 288.",
+              "nullable": true,
+              "range": "[1,*)",
+              "integral": true,
+              "units": "blocks",
+            },
+            "max-tasks-in-mem-queue": {
+              "type": "number",
+              "name": "Max Tasks in Memory Queue",
+              "description":
+                "Number of backup tasks kept in memory before they overflow to disk. Raise it on busy routing nodes. Leave empty for LND's default of 2000.",
+              "nullable": true,
+              "range": "[1,*)",
+              "integral": true,
+            },
+            "max-updates": {
+              "type": "number",
+              "name": "Max Updates per Session",
+              "description":
+                "Number of channel state updates a session with a tower can hold before a new session is negotiated. Leave empty for LND's default of 1024.\n\nSession types are picked automatically: anchor channels get anchor sessions and taproot channels get taproot sessions, provided the tower supports them. A tower that does not support a channel's type cannot back that channel up.",
+              "nullable": true,
+              "range": "[1,65535]",
+              "integral": true,
+            },
           }
         }
       },
     },
   },
+  "macaroons": {
+    "type": "object",
+    "name": "Macaroons",
+    "description":
+      "Credentials published to services that depend on LND. Each profile is baked with only the permissions it needs, from its own root key, so it can be revoked without affecting any other.",
+    "spec": {
+      "publish-default-macaroons": {
+        "type": "boolean",
+        "name": "Publish Default Macaroons",
+        "description":
+          "Share the macaroons LND creates itself, including admin.macaroon, with dependent services. Disable this once every dependent uses a profile below.",
+        "default": true,
+      },
+      "profiles": {
+        "type": "list",
+        "subtype": "object",
+        "name": "Macaroon Profiles",
+        "description":
+          "Restricted macaroons published as <name>.macaroon to dependent services.",
+        "range": "[0,*)",
+        "default": Array<Record<string, unknown>>(),
+        "spec": {
+          "unique-by": "name",
+          "display-as": "{{name}}",
+          "spec": {
+            "name": {
+              "type": "string",
+              "name": "Name",
+              "description":
+                "File name of the published macaroon, without the .macaroon extension.",
+              "nullable": false,
+              "pattern": "^[a-z0-9][a-z0-9-]*$",
+              "pattern-description":
+                "Lowercase letters, digits and dashes only.",
+            },
+            "preset": {
+              "type": "enum",
+              "name": "Permissions",
+              "description":
+                "Read Only: can see everything, change nothing.\nInvoices: create invoices and receive addresses.\nPayments: send and receive over Lightning. LND cannot cap the amount a macaroon may spend.\nCustom: the permissions listed below.",
+              "values": ["readonly", "invoices", "payments", "custom"],
+              "value-names": {
+                "readonly": "Read Only",
+                "invoices": "Invoices",
+                "payments": "Payments",
+                "custom": "Custom",
+              },
+              "default": "readonly",
+            },
+            "custom-permissions": {
+              "type": "list",
+              "subtype": "string",
+              "name": "Custom Permissions",
+              "description":
+                "Permissions as entity:action, for example invoices:read. Only used with the Custom preset.",
+              "range": "[0,*)",
+              "default": Array<string>(),
+              "spec": {
+                "masked": false,
+                "copyable": false,
+                "placeholder": "invoices:read",
+                "pattern": "^[a-z]+:[a-z]+$",
+                "pattern-description": "entity:action, for example invoices:read",
+              },
+            },
+            "timeout": {
+              "type": "number",
+              "name": "Expiry",
+              "description":
+                "Published macaroons stop working after this many hours. They are re-issued automatically well before they expire, so dependents must re-read the file instead of caching it. Leave empty for macaroons that never expire.",
+              "nullable": true,
+              "range": "[1,8760]",
+              "integral": true,
+              "units": "hours",
+            },
+            "ip-lock": {
+              "type": "string",
+              "name": "Lock to Service",
+              "description":
+                "Package id of the service using this macaroon, for example btcpayserver. The macaroon will only be accepted from that service's container IP, and is re-issued when the IP changes. While the service is not running, the macaroon is not published.",
+              "nullable": true,
+              "pattern": "^[a-z0-9][a-z0-9-]*$",
+              "pattern-description": "A package id: lowercase letters, digits and dashes",
+            },
+          },
+        },
+      },
+    },
+  },
   "advanced": {
     "type": "object",
     "name": "Advanced",
@@ -622,6 +855,67 @@ export const getConfig: T.ExpectedExports.getConfig = compat.getConfig({
           },
         }
       },
+      "rpc-interface": {
+        "type": "string",
+        "name": "API Network Interface",
+        "description":
+          "Network interface whose IPv4 and IPv6 addresses the gRPC (10009) and REST (8080) APIs listen on. Only change this if you know your container's network layout.",
+        "nullable": false,
+        "default": "eth0",
+        "pattern": "^[A-Za-z0-9_.@-]{1,15}$",
+        "pattern-description": "A Linux interface name, for example eth0",
+      },
+      "health-check": {
+        "type": "object",
+        "name": "Health Check Options",
+        "description": "Thresholds used by the LND health checks.",
+        "spec": {
+          "max-block-lag": {
+            "type": "number",
+            "name": "Maximum Block Lag",
+            "description":
+              "How many blocks LND may fall behind Bitcoin Core before the Chain Sync health check fails. LND always trails a new block briefly, so a small lag is only reported, not treated as a failure.",
+            "nullable": false,
+            "range": "[1,*)",
+            "integral": true,
+            "default": 6,
+            "units": "blocks",
+          },
+          "max-inactive-channels": {
+            "type": "number",
+            "name": "Maximum Inactive Channels",
+            "description":
+              "The share of channels that may be inactive, for example because their peers are offline, before the Channels health check raises a warning.",
+            "nullable": false,
+            "range": "[0,100]",
+            "integral": true,
+            "default": 50,
+            "units": "%",
+          },
+          "disk-free-warn": {
+            "type": "number",
+            "name": "Low Disk Space Warning",
+            "description":
+              "The Disk health check warns when less than this much space is free on the LND volume.",
+            "nullable": false,
+            "range": "[0,*)",
+            "integral": true,
+            "default": 10,
+            "units": "GB",
+          },
+          "disk-free-fail": {
+            "type": "number",
+            "name": "Low Disk Space Failure",
+            "description":
+              "The Disk health check fails when less than this much space is free on the LND volume. LND can corrupt its database if the disk fills up completely.",
+            "nullable": false,
+            "range": "[0,*)",
+            "integral": true,
+            "default": 2,
+            "units": "GB",
+          },
+        },
+      },
     },
   },
 });
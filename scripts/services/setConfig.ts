@@ -1,6 +1,64 @@
 import { compat, types as T } from "../deps.ts";
 import { matchRoot, Root } from "../models/setConfig.ts";
 
+// Mirrors WatchtowerUri::from_str in the configurator
+function watchtowerUriError(uri: string): string | void {
+  const at = uri.indexOf("@");
+  if (at < 0) return `Watchtower URI ${uri} must have the form pubkey@host:port`;
+  const pubkey = uri.slice(0, at);
+  const address = uri.slice(at + 1);
+  if (!/^0[23][0-9a-fA-F]{64}$/.test(pubkey)) {
+    return `Watchtower pubkey ${pubkey} must be a 33-byte compressed public key in hex (66 characters starting with 02 or 03)`;
+  }
+  return addressError(address);
+}
+
+// Mirrors net::parse_address in the configurator
+function addressError(address: string): string | void {
+  let host = address;
+  let port: string | undefined;
+  const bracketed = address.match(/^\[([^\]]*)\](?::(.*))?$/);
+  if (address.startsWith("[")) {
+    if (!bracketed) return `IPv6 address ${address} is not valid`;
+    host = bracketed[1];
+    port = bracketed[2];
+    if (!host.includes(":")) return `${host} is not a valid IPv6 address`;
+  } else if (address.split(":").length > 2) {
+    return `IPv6 address ${address} must be enclosed in brackets`;
+  } else if (address.includes(":")) {
+    [host, port] = address.split(":");
+  }
+  if (
+    port !== undefined &&
+    !(/^[0-9]+$/.test(port) && Number(port) >= 1 && Number(port) <= 65535)
+  ) {
+    return `${port} in ${address} is not a valid port`;
+  }
+  if (bracketed) return;
+  const name = host.toLowerCase();
+  if (/^[0-9.]+$/.test(name)) {
+    const octets = name.split(".");
+    if (octets.length !== 4 || octets.some((o) => o === "" || Number(o) > 255)) {
+      return `${host} is not a valid IPv4 address`;
+    }
+    return;
+  }
+  if (name.endsWith(".onion")) {
+    if (!/^[a-z2-7]{55}d\.onion$/.test(name)) {
+      return `${host} is not a v3 onion address`;
+    }
+    return;
+  }
+  if (
+    name.length > 253 ||
+    !name.split(".").every((label) =>
+      /^[a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?$/.test(label)
+    )
+  ) {
+    return `${host} is not a valid host name`;
+  }
+}
+
 type Check = {
   currentError(config: Root): string | void;
 };
@@ -22,6 +80,30 @@ const configRules: Array<Check> = [
       }
     },
   },
+  {
+    currentError(config) {
+      if (config.tor.hybrid?.enabled === "enabled") {
+        if (config.tor["use-tor-only"]) {
+          return "'Tor Config > Use Tor for all traffic' must be disabled to enable 'Tor Config > Clearnet (Hybrid Mode)'";
+        }
+        if (config.tor["stream-isolation"]) {
+          return "'Tor Config > Stream Isolation' must be disabled to enable 'Tor Config > Clearnet (Hybrid Mode)'";
+        }
+      }
+    },
+  },
+  {
+    currentError(config) {
+      const hybrid = config.tor.hybrid;
+      if (hybrid?.enabled !== "enabled") return;
+      for (const host of hybrid["external-hosts"] ?? []) {
+        const error = addressError(host.trim());
+        if (error) {
+          return `'Tor Config > Clearnet (Hybrid Mode) > External Hosts': ${error}`;
+        }
+      }
+    },
+  },
   {
     currentError(config) {
       if (config["max-chan-size"]) {
@@ -45,6 +127,42 @@ const configRules: Array<Check> = [
       }
     },
   },
+  {
+    currentError(config) {
+      for (const host of config.watchtowers?.["wt-server-settings"]?.["external-hosts"] ?? []) {
+        const error = addressError(host.trim());
+        if (error) {
+          return `'Watchtowers > Watchtower Server Settings > External Hosts': ${error}`;
+        }
+      }
+    },
+  },
+  {
+    currentError(config) {
+      for (const uri of config.watchtowers?.["wt-client"]["add-watchtowers"] ?? []) {
+        const error = watchtowerUriError(uri.trim());
+        if (error) {
+          return `'Watchtowers > Watchtower Client > Add Watchtowers': ${error}`;
+        }
+      }
+    },
+  },
+  {
+    currentError(config) {
+      for (const profile of config.macaroons?.profiles ?? []) {
+        if (profile.preset !== "custom") continue;
+        const permissions = profile["custom-permissions"];
+        if (permissions.length === 0) {
+          return `'Macaroons > Profiles > ${profile.name}': the Custom preset needs at least one permission`;
+        }
+        for (const permission of permissions) {
+          if (!/^[a-z]+:[a-z]+$/.test(permission)) {
+            return `'Macaroons > Profiles > ${profile.name}': ${permission} must have the form entity:action, for example invoices:read`;
+          }
+        }
+      }
+    },
+  },
 ];
 
 function checkConfigRules(config: Root): T.KnownError | void {